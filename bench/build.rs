@@ -13,19 +13,22 @@ fn main() {
     let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
 
     // =========================================================================
-    // Build rstcmalloc as THREE staticlibs with the `fast` profile:
+    // Build rstcmalloc as FOUR staticlibs with the `fast` profile:
     //   - nightly (#[thread_local] thread cache): --features nightly,ffi,testing
-    //   - std     (std::thread_local! cache):     --features std,ffi,testing
-    //   - nostd   (central cache only):           --features ffi,testing
+    //   - percpu  (rseq-backed per-CPU slabs):     --features nightly,percpu,ffi,testing
+    //   - std     (std::thread_local! cache):      --features std,ffi,testing
+    //   - nostd   (central cache only):            --features ffi,testing
     // =========================================================================
 
     build_variant(&cargo, &ws_root, &out_dir, "nightly,ffi,testing", "rstcmalloc_nightly");
+    build_variant(&cargo, &ws_root, &out_dir, "nightly,percpu,ffi,testing", "rstcmalloc_percpu");
     build_variant(&cargo, &ws_root, &out_dir, "std,ffi,testing", "rstcmalloc_std");
     build_variant(&cargo, &ws_root, &out_dir, "ffi,testing", "rstcmalloc_nostd");
 
-    // Link all three variants
+    // Link all four variants
     println!("cargo:rustc-link-search=native={}", out_dir.display());
     println!("cargo:rustc-link-lib=static=rstcmalloc_nightly");
+    println!("cargo:rustc-link-lib=static=rstcmalloc_percpu");
     println!("cargo:rustc-link-lib=static=rstcmalloc_std");
     println!("cargo:rustc-link-lib=static=rstcmalloc_nostd");
 
@@ -20,13 +20,22 @@ use crate::thread_cache::ThreadCache;
 use crate::transfer_cache::TransferCacheArray;
 use crate::PAGE_SHIFT;
 use crate::PAGE_SIZE;
+#[cfg(feature = "nightly")]
+use core::alloc::{AllocError, Allocator};
 use core::alloc::{GlobalAlloc, Layout};
+#[cfg(feature = "nightly")]
+use core::ptr::NonNull;
 use core::ptr;
 
 // =============================================================================
 // Global static state
 // =============================================================================
 
+/// Reallocations of mmap-backed large spans at or above this size use
+/// `mremap` instead of a copy, mirroring hardened_malloc's large-allocation
+/// threshold.
+const MREMAP_THRESHOLD: usize = 32 * 1024 * 1024; // 32 MiB
+
 static PAGE_MAP: PageMap = PageMap::new();
 static PAGE_HEAP: SpinMutex<PageHeap> = SpinMutex::new(PageHeap::new(&PAGE_MAP));
 static CENTRAL_CACHE: CentralCache = CentralCache::new();
@@ -58,6 +67,10 @@ unsafe fn get_tc() -> &'static mut ThreadCache {
 #[inline(never)]
 fn tc_init_cold(tc: &mut ThreadCache) {
     tc.init();
+    // Join the global budget-sharing registry; `tc` is `&mut` to the
+    // thread's own `#[thread_local]` static, which outlives every call made
+    // through it for the life of the thread.
+    unsafe { crate::thread_cache::register_current_thread(tc as *mut ThreadCache) };
 }
 
 // =============================================================================
@@ -78,16 +91,20 @@ unsafe impl GlobalAlloc for TcMalloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let size = layout.size();
         if size == 0 {
+            crate::stats::record_zero_size_alloc();
             return layout.align() as *mut u8;
         }
 
         let align = layout.align();
 
         if align <= 8 {
-            // Fast path: all size classes are 8-aligned, no alignment check needed
-            let class = size_class::size_to_class(size);
-            if class != 0 {
-                return unsafe { self.alloc_small(class) };
+            // Fast path: all size classes are 8-aligned, no alignment check
+            // needed. `Some` means `size` classified as small -- the
+            // contained pointer is the final answer even if it's null (an
+            // allocation failure for a valid class, not a cue to fall back
+            // to the large path); `None` means `size` belongs on that path.
+            if let Some(ptr) = unsafe { self.alloc_small_sized(size) } {
+                return ptr;
             }
         } else {
             // Rare path: alignment > 8
@@ -129,11 +146,38 @@ unsafe impl GlobalAlloc for TcMalloc {
     }
 
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        let ptr = unsafe { self.alloc(layout) };
-        if !ptr.is_null() && layout.size() > 0 {
-            unsafe { ptr::write_bytes(ptr, 0, layout.size()) };
+        let size = layout.size();
+        if size == 0 {
+            crate::stats::record_zero_size_alloc();
+            return layout.align() as *mut u8;
         }
-        ptr
+
+        let align = layout.align();
+
+        if align <= 8 {
+            // Small objects: skip the memset when the thread cache can prove
+            // the object came from a span fresh from the OS and was never
+            // recycled.
+            let class = size_class::size_to_class(size);
+            if class != 0 {
+                return unsafe { self.alloc_small_zeroed(class) };
+            }
+        } else {
+            let effective_size = size.max(align);
+            let class = size_class::size_to_class(effective_size);
+            if class != 0 {
+                let class_size = size_class::class_to_size(class);
+                if class_size % align == 0 {
+                    return unsafe { self.alloc_small_zeroed(class) };
+                }
+                // Falls through to the large path below.
+                return unsafe { self.alloc_large(layout) };
+            }
+        }
+
+        // Large allocations come straight from the page heap, which in turn
+        // gets fresh pages from mmap/VirtualAlloc -- already zeroed by the OS.
+        unsafe { self.alloc_large(layout) }
     }
 
     unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
@@ -153,12 +197,12 @@ unsafe impl GlobalAlloc for TcMalloc {
         if align <= 8 {
             let old_class = size_class::size_to_class(layout.size());
             if old_class != 0 {
-                let current_size = size_class::class_to_size(old_class);
-                if new_size <= current_size {
-                    return ptr;
-                }
-                let new_class = size_class::size_to_class(new_size);
-                if new_class == old_class {
+                // `resize_in_place` already knows exactly this branch's
+                // logic (fits the current class -> free grow, otherwise 0);
+                // call it instead of keeping a second copy here to drift out
+                // of sync.
+                let usable = unsafe { self.resize_in_place(ptr, layout, new_size) };
+                if usable > 0 {
                     return ptr;
                 }
                 // Need new allocation
@@ -180,49 +224,250 @@ unsafe impl GlobalAlloc for TcMalloc {
 }
 
 impl TcMalloc {
+    /// Small allocation: per-CPU slab (nightly + percpu), falling back to the
+    /// thread cache on kernels without `rseq`.
+    #[cfg(all(feature = "nightly", feature = "percpu"))]
+    #[inline(always)]
+    unsafe fn alloc_small(&self, class: usize) -> *mut u8 {
+        let ptr = if crate::platform::rseq_available() {
+            unsafe { crate::percpu_cache::allocate(class, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) }
+        } else {
+            let tc = unsafe { get_tc() };
+            unsafe { tc.allocate(class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) }
+        };
+        #[cfg(feature = "hardened")]
+        if !ptr.is_null() {
+            unsafe { crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class)) };
+        }
+        crate::stats::record_small_alloc(class);
+        ptr
+    }
+
     /// Small allocation: thread cache (nightly) or central cache (no_std fallback).
-    #[cfg(feature = "nightly")]
+    #[cfg(all(feature = "nightly", not(feature = "percpu")))]
     #[inline(always)]
     unsafe fn alloc_small(&self, class: usize) -> *mut u8 {
         let tc = unsafe { get_tc() };
-        unsafe { tc.allocate(class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) }
+        let ptr = unsafe { tc.allocate(class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) };
+        #[cfg(feature = "hardened")]
+        if !ptr.is_null() {
+            unsafe { crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class)) };
+        }
+        crate::stats::record_small_alloc(class);
+        ptr
     }
 
     #[cfg(not(feature = "nightly"))]
     #[inline(always)]
     unsafe fn alloc_small(&self, class: usize) -> *mut u8 {
-        unsafe { self.alloc_from_central(class) }
+        let ptr = unsafe { self.alloc_from_central(class) };
+        #[cfg(feature = "hardened")]
+        if !ptr.is_null() {
+            unsafe { crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class)) };
+        }
+        crate::stats::record_small_alloc(class);
+        ptr
+    }
+
+    /// Small allocation from a raw size, for `GlobalAlloc::alloc`'s hot
+    /// `align <= 8` path. `None` means `size` doesn't fit a small class --
+    /// the caller should fall back to the large-object path. `Some(ptr)` is
+    /// the final answer even when `ptr` is null (an allocation failure for a
+    /// valid class is not a cue to retry as a large allocation).
+    ///
+    /// On the thread-cache fast path, this wires
+    /// [`ThreadCache::allocate_size`](crate::thread_cache::ThreadCache::allocate_size)
+    /// straight in front of `lists[size_class]`, so classifying `size` and
+    /// looking the class back up in the thread cache happens in one table
+    /// load instead of two separate calls.
+    #[cfg(all(feature = "nightly", not(feature = "percpu")))]
+    #[inline(always)]
+    unsafe fn alloc_small_sized(&self, size: usize) -> Option<*mut u8> {
+        let tc = unsafe { get_tc() };
+        let (ptr, class) = unsafe { tc.allocate_size(size, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) };
+        if class == 0 {
+            return None;
+        }
+        if !ptr.is_null() {
+            #[cfg(feature = "hardened")]
+            unsafe {
+                crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class))
+            };
+            crate::stats::record_small_alloc(class);
+        }
+        Some(ptr)
+    }
+
+    /// Small allocation from a raw size: per-CPU slab / no_std fallback
+    /// variants still classify via [`size_class::class_index_maybe`] and
+    /// dispatch to [`alloc_small`](Self::alloc_small), since neither routes
+    /// through a `ThreadCache` directly the way the plain nightly fast path
+    /// does.
+    #[cfg(not(all(feature = "nightly", not(feature = "percpu"))))]
+    #[inline(always)]
+    unsafe fn alloc_small_sized(&self, size: usize) -> Option<*mut u8> {
+        size_class::class_index_maybe(size).map(|class| unsafe { self.alloc_small(class) })
+    }
+
+    /// Small zeroed allocation: per-CPU slab (nightly + percpu) or thread
+    /// cache fallback, skipping the memset when the object is provably
+    /// still all-zero.
+    #[cfg(all(feature = "nightly", feature = "percpu"))]
+    #[inline(always)]
+    unsafe fn alloc_small_zeroed(&self, class: usize) -> *mut u8 {
+        let (ptr, fresh) = if crate::platform::rseq_available() {
+            unsafe {
+                crate::percpu_cache::allocate_maybe_fresh(class, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+            }
+        } else {
+            let tc = unsafe { get_tc() };
+            unsafe {
+                tc.allocate_maybe_fresh(class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+            }
+        };
+        if !ptr.is_null() {
+            #[cfg(feature = "hardened")]
+            unsafe {
+                crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class))
+            };
+            if !fresh {
+                unsafe { ptr::write_bytes(ptr, 0, size_class::class_to_size(class)) };
+            }
+            crate::stats::record_small_alloc(class);
+        }
+        ptr
+    }
+
+    /// Small zeroed allocation: skips the memset when the object is
+    /// provably still all-zero (see `ThreadCache::allocate_maybe_fresh`).
+    #[cfg(all(feature = "nightly", not(feature = "percpu")))]
+    #[inline(always)]
+    unsafe fn alloc_small_zeroed(&self, class: usize) -> *mut u8 {
+        let tc = unsafe { get_tc() };
+        let (ptr, fresh) = unsafe {
+            tc.allocate_maybe_fresh(class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+        };
+        if !ptr.is_null() {
+            #[cfg(feature = "hardened")]
+            unsafe {
+                crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class))
+            };
+            if !fresh {
+                unsafe { ptr::write_bytes(ptr, 0, size_class::class_to_size(class)) };
+            }
+            crate::stats::record_small_alloc(class);
+        }
+        ptr
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    #[inline(always)]
+    unsafe fn alloc_small_zeroed(&self, class: usize) -> *mut u8 {
+        let (ptr, fresh) = unsafe { self.alloc_from_central_maybe_fresh(class) };
+        if !ptr.is_null() {
+            #[cfg(feature = "hardened")]
+            unsafe {
+                crate::hardened::verify_on_alloc(ptr, size_class::class_to_size(class))
+            };
+            if !fresh {
+                unsafe { ptr::write_bytes(ptr, 0, size_class::class_to_size(class)) };
+            }
+            crate::stats::record_small_alloc(class);
+        }
+        ptr
+    }
+
+    /// Small deallocation: per-CPU slab (nightly + percpu), falling back to
+    /// the thread cache on kernels without `rseq`.
+    #[cfg(all(feature = "nightly", feature = "percpu"))]
+    #[inline(always)]
+    unsafe fn dealloc_small(&self, ptr: *mut u8, class: usize) {
+        crate::stats::record_small_free(class);
+        #[cfg(feature = "hardened")]
+        unsafe {
+            crate::hardened::scrub_on_free(ptr, size_class::class_to_size(class))
+        };
+        #[cfg(feature = "quarantine")]
+        let released = crate::quarantine::pass_through(class, ptr);
+        #[cfg(not(feature = "quarantine"))]
+        let released = ptr;
+        if released.is_null() {
+            return;
+        }
+        if crate::platform::rseq_available() {
+            unsafe {
+                crate::percpu_cache::deallocate(released, class, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+            };
+        } else {
+            let tc = unsafe { get_tc() };
+            unsafe {
+                tc.deallocate(released, class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+            };
+        }
     }
 
     /// Small deallocation: thread cache (nightly) or central cache (no_std fallback).
-    #[cfg(feature = "nightly")]
+    #[cfg(all(feature = "nightly", not(feature = "percpu")))]
     #[inline(always)]
     unsafe fn dealloc_small(&self, ptr: *mut u8, class: usize) {
+        crate::stats::record_small_free(class);
+        #[cfg(feature = "hardened")]
+        unsafe {
+            crate::hardened::scrub_on_free(ptr, size_class::class_to_size(class))
+        };
+        #[cfg(feature = "quarantine")]
+        let released = crate::quarantine::pass_through(class, ptr);
+        #[cfg(not(feature = "quarantine"))]
+        let released = ptr;
+        if released.is_null() {
+            return;
+        }
         let tc = unsafe { get_tc() };
         unsafe {
-            tc.deallocate(ptr, class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
+            tc.deallocate(released, class, &TRANSFER_CACHE, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP)
         };
     }
 
     #[cfg(not(feature = "nightly"))]
     #[inline(always)]
     unsafe fn dealloc_small(&self, ptr: *mut u8, class: usize) {
-        unsafe { self.dealloc_to_central(ptr, class) };
+        crate::stats::record_small_free(class);
+        #[cfg(feature = "hardened")]
+        unsafe {
+            crate::hardened::scrub_on_free(ptr, size_class::class_to_size(class))
+        };
+        #[cfg(feature = "quarantine")]
+        let released = crate::quarantine::pass_through(class, ptr);
+        #[cfg(not(feature = "quarantine"))]
+        let released = ptr;
+        if released.is_null() {
+            return;
+        }
+        unsafe { self.dealloc_to_central(released, class) };
     }
 
     /// Allocate from central cache directly (no thread cache).
     #[cfg(not(feature = "nightly"))]
     unsafe fn alloc_from_central(&self, size_class: usize) -> *mut u8 {
-        let (count, head) = unsafe {
+        let (ptr, _fresh) = unsafe { self.alloc_from_central_maybe_fresh(size_class) };
+        ptr
+    }
+
+    /// Like `alloc_from_central`, but also reports whether the object is
+    /// known-zero (carved from a span fresh from the OS). Used by
+    /// `alloc_zeroed` to skip the memset.
+    #[cfg(not(feature = "nightly"))]
+    unsafe fn alloc_from_central_maybe_fresh(&self, size_class: usize) -> (*mut u8, bool) {
+        let (count, head, fresh) = unsafe {
             CENTRAL_CACHE
                 .get(size_class)
                 .lock()
                 .remove_range(1, &PAGE_HEAP, &PAGE_MAP)
         };
         if count == 0 || head.is_null() {
-            ptr::null_mut()
+            (ptr::null_mut(), false)
         } else {
-            head as *mut u8
+            (head as *mut u8, fresh)
         }
     }
 
@@ -251,6 +496,8 @@ impl TcMalloc {
         let sc = unsafe { (*span).size_class };
 
         if sc == 0 {
+            let pages = unsafe { (*span).num_pages };
+            crate::stats::record_large_free(pages);
             unsafe { PAGE_HEAP.lock().deallocate_span(span) };
         } else {
             unsafe { self.dealloc_small(ptr, sc) };
@@ -284,6 +531,15 @@ impl TcMalloc {
                 if new_size <= span_bytes {
                     return ptr;
                 }
+
+                // Large mmap-backed spans at or above the threshold can grow
+                // via mremap(MREMAP_MAYMOVE) instead of a copy.
+                if span_bytes >= MREMAP_THRESHOLD {
+                    if let Some(new_ptr) = unsafe { self.mremap_large(span, span_bytes, new_size) }
+                    {
+                        return new_ptr;
+                    }
+                }
             }
         }
 
@@ -297,6 +553,278 @@ impl TcMalloc {
         new_ptr
     }
 
+    /// Grow or relocate an mmap-backed large span via `mremap`, avoiding the
+    /// copy the generic fallback would otherwise perform.
+    ///
+    /// Returns `None` on platforms without `mremap` (the copy fallback in
+    /// `realloc_slow` runs instead) or if the remap itself fails, in which
+    /// case the span is left untouched.
+    #[cfg(target_os = "linux")]
+    unsafe fn mremap_large(
+        &self,
+        span: *mut crate::span::Span,
+        old_bytes: usize,
+        new_size: usize,
+    ) -> Option<*mut u8> {
+        let new_pages = (new_size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let new_bytes = new_pages * PAGE_SIZE;
+        let old_addr = unsafe { (*span).start_addr() };
+        let old_pages = unsafe { (*span).num_pages };
+
+        let new_addr = unsafe { crate::platform::mremap(old_addr, old_bytes, new_bytes) }?;
+
+        unsafe {
+            PAGE_MAP.unregister_span(span);
+            (*span).set_start_addr(new_addr);
+            (*span).num_pages = new_pages;
+            PAGE_MAP.register_span(span);
+        }
+
+        // The span itself stays live throughout -- `live_large_spans` is
+        // unaffected -- but its page count just changed, so the live-byte
+        // total needs the same delta applied directly instead of going
+        // through a free+alloc pair that would double-count the span.
+        crate::stats::adjust_large_live_pages(new_pages as i64 - old_pages as i64);
+
+        Some(new_addr)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    unsafe fn mremap_large(
+        &self,
+        _span: *mut crate::span::Span,
+        _old_bytes: usize,
+        _new_size: usize,
+    ) -> Option<*mut u8> {
+        None
+    }
+
+    /// In-place resize (xallocx-style): report the usable size actually
+    /// available at `ptr` without moving or copying anything.
+    ///
+    /// For small allocations this is simply the current size class's size,
+    /// since every object in a class already occupies the full class size --
+    /// growing up to that size is free. For large allocations it is the
+    /// backing span's full page-rounded size. Following jemalloc's `xallocx`,
+    /// this partially fulfills: even when `new_size` doesn't fit, a smaller
+    /// usable size within the same bucket is still reported rather than
+    /// failing outright whenever one is available. Returns 0 when no
+    /// in-place resize is possible and the caller must fall back to `realloc`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for `layout`.
+    pub unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> usize {
+        let align = layout.align();
+
+        if align <= 8 {
+            let class = size_class::size_to_class(layout.size());
+            if class != 0 {
+                let class_size = size_class::class_to_size(class);
+                return if new_size <= class_size { class_size } else { 0 };
+            }
+        }
+
+        // Large allocation or align > 8: look the span up in the page map.
+        let page_id = (ptr as usize) >> PAGE_SHIFT;
+        let span = PAGE_MAP.get(page_id);
+        if span.is_null() {
+            return 0;
+        }
+
+        let sc = unsafe { (*span).size_class };
+        if sc != 0 {
+            let class_size = size_class::class_to_size(sc);
+            return if new_size <= class_size { class_size } else { 0 };
+        }
+
+        let span_bytes = unsafe { (*span).num_pages } * PAGE_SIZE;
+        if new_size <= span_bytes {
+            span_bytes
+        } else {
+            0
+        }
+    }
+
+    /// Deallocate `ptr` without knowing its original size or alignment,
+    /// recovering both via the page map. Used by the `libc-shim` `free`
+    /// export, which (per the C ABI) receives no size.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or have been returned by this allocator.
+    #[cfg(feature = "libc-shim")]
+    pub unsafe fn dealloc_unsized(&self, ptr: *mut u8) {
+        if ptr.is_null() {
+            return;
+        }
+        unsafe { self.dealloc_slow(ptr) };
+    }
+
+    /// Usable size of a pointer previously returned by this allocator,
+    /// recovered via the page map the same way `dealloc_slow` does. Used by
+    /// the `libc-shim` `malloc_usable_size`/`realloc` exports.
+    #[cfg(feature = "libc-shim")]
+    pub fn usable_size(&self, ptr: *mut u8) -> usize {
+        if ptr.is_null() {
+            return 0;
+        }
+        let page_id = (ptr as usize) >> PAGE_SHIFT;
+        let span = PAGE_MAP.get(page_id);
+        if span.is_null() {
+            return 0;
+        }
+        let sc = unsafe { (*span).size_class };
+        if sc != 0 {
+            size_class::class_to_size(sc)
+        } else {
+            unsafe { (*span).num_pages } * PAGE_SIZE
+        }
+    }
+
+    /// Bulk allocation: hand back up to `n` objects of `size_class` as a
+    /// single linked list in one call, for a raw-buffer grower (e.g. a
+    /// `Vec`/`HashMap` reallocation) that already knows it wants many
+    /// objects of one class and would otherwise pay the free-list-head cost
+    /// of `n` separate `alloc` calls. Returns the actual count obtained,
+    /// which is less than `n` only if the allocator ran short, and the head
+    /// of the list, threaded through each object's first word exactly like
+    /// `ThreadCache`'s own free lists. Nightly only: the no_std fallback has
+    /// no thread cache to batch against.
+    ///
+    /// Every object in the batch is verified under `hardened`, exactly like
+    /// `alloc_small` does for a single object -- a bulk caller gets the same
+    /// write-after-free detection as anyone going through one `alloc` at a
+    /// time.
+    #[cfg(feature = "nightly")]
+    pub unsafe fn alloc_batch(&self, size_class: usize, n: u32) -> (u32, *mut u8) {
+        let tc = unsafe { get_tc() };
+        let (count, head) =
+            unsafe { tc.allocate_batch(size_class, n, &CENTRAL_CACHE, &PAGE_HEAP, &PAGE_MAP) };
+
+        #[cfg(feature = "hardened")]
+        if !head.is_null() {
+            let class_size = size_class::class_to_size(size_class);
+            let mut node = head;
+            while !node.is_null() {
+                let next = unsafe { (*node).next };
+                unsafe { crate::hardened::verify_on_alloc(node as *mut u8, class_size) };
+                node = next;
+            }
+        }
+
+        for _ in 0..count {
+            crate::stats::record_small_alloc(size_class);
+        }
+        (count, head as *mut u8)
+    }
+
+    /// Bulk deallocation: the inverse of [`alloc_batch`](Self::alloc_batch) --
+    /// return a linked list of `count` objects of `size_class`, threaded
+    /// through `next` pointers the same way `alloc_batch` returned them, with
+    /// one `total_size` adjustment instead of `count` separate ones.
+    ///
+    /// Every object is run through `hardened`'s scrub-on-free and the free
+    /// quarantine individually, exactly as `dealloc_small` does for a single
+    /// object -- otherwise a bulk caller would silently bypass both. Since
+    /// the quarantine can absorb some objects and release unrelated older
+    /// ones in their place, the batch handed to `ThreadCache::deallocate_batch`
+    /// is rebuilt from whatever the quarantine actually released rather than
+    /// being the original list unchanged.
+    ///
+    /// # Safety
+    ///
+    /// `head` must be null or a linked list of exactly `count` objects, each
+    /// previously returned by this allocator for `size_class`.
+    #[cfg(feature = "nightly")]
+    pub unsafe fn dealloc_batch(&self, head: *mut u8, count: u32, size_class: usize) {
+        if head.is_null() || count == 0 {
+            return;
+        }
+        for _ in 0..count {
+            crate::stats::record_small_free(size_class);
+        }
+
+        #[cfg(feature = "hardened")]
+        let class_size = size_class::class_to_size(size_class);
+
+        let mut node = head as *mut crate::span::FreeObject;
+        let mut released_head: *mut crate::span::FreeObject = ptr::null_mut();
+        let mut released_count = 0u32;
+        while !node.is_null() {
+            let next = unsafe { (*node).next };
+
+            #[cfg(feature = "hardened")]
+            unsafe {
+                crate::hardened::scrub_on_free(node as *mut u8, class_size)
+            };
+
+            #[cfg(feature = "quarantine")]
+            let released = crate::quarantine::pass_through(size_class, node as *mut u8);
+            #[cfg(not(feature = "quarantine"))]
+            let released = node as *mut u8;
+
+            if !released.is_null() {
+                let released = released as *mut crate::span::FreeObject;
+                unsafe { (*released).next = released_head };
+                released_head = released;
+                released_count += 1;
+            }
+
+            node = next;
+        }
+
+        if released_head.is_null() {
+            return;
+        }
+
+        let tc = unsafe { get_tc() };
+        unsafe {
+            tc.deallocate_batch(
+                released_head,
+                released_count,
+                size_class,
+                &CENTRAL_CACHE,
+                &PAGE_HEAP,
+                &PAGE_MAP,
+            )
+        };
+    }
+
+    /// Full memory-introspection snapshot: per-class counters plus idle
+    /// bytes at every cache tier and total bytes mapped in from the OS.
+    /// Modeled on tcmalloc's `MallocExtension::GetStats`.
+    pub fn memory_stats(&self) -> crate::stats::MemoryStats {
+        let central_cache_bytes = CENTRAL_CACHE.cached_bytes();
+        #[cfg(feature = "nightly")]
+        let transfer_cache_bytes = TRANSFER_CACHE.cached_bytes();
+        #[cfg(not(feature = "nightly"))]
+        let transfer_cache_bytes = 0u64;
+
+        let (mapped_bytes, free_bytes) = {
+            let heap = PAGE_HEAP.lock();
+            (heap.mapped_bytes(), heap.free_bytes())
+        };
+
+        crate::stats::build_memory_stats(
+            central_cache_bytes,
+            transfer_cache_bytes,
+            mapped_bytes,
+            free_bytes,
+        )
+    }
+
+    /// Return-to-OS API (tcmalloc's `MallocExtension::ReleaseFreeMemory`):
+    /// decommit every free span currently held in the page heap, shrinking
+    /// RSS without giving back the address space. Decommitted spans are
+    /// transparently recommitted (and, on Windows, re-faulted) the next
+    /// time they're handed out.
+    ///
+    /// Returns the number of bytes decommitted.
+    pub fn release_memory(&self) -> usize {
+        PAGE_HEAP.lock().release_free_memory()
+    }
+
     /// Large allocation: allocate directly from page heap.
     unsafe fn alloc_large(&self, layout: Layout) -> *mut u8 {
         let size = layout.size();
@@ -313,6 +841,8 @@ impl TcMalloc {
             PAGE_MAP.register_span(span);
         }
 
+        crate::stats::record_large_alloc(pages);
+
         let addr = unsafe { (*span).start_addr() };
 
         if align <= PAGE_SIZE {
@@ -326,3 +856,243 @@ impl TcMalloc {
         addr
     }
 }
+
+// =============================================================================
+// allocator_api handle (nightly only)
+// =============================================================================
+
+/// Zero-sized handle implementing the nightly `core::alloc::Allocator` trait.
+///
+/// Unlike `TcMalloc`, which is meant to be registered as `#[global_allocator]`
+/// for the whole process, `RtAllocator` lets individual collections opt into
+/// the thread-cache fast path without affecting anything else:
+/// ```ignore
+/// #![feature(allocator_api)]
+/// let v: Vec<u8, _> = Vec::new_in(rstcmalloc::allocator::RtAllocator);
+/// ```
+#[cfg(feature = "nightly")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RtAllocator;
+
+#[cfg(feature = "nightly")]
+unsafe impl Allocator for RtAllocator {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        rt_allocate(layout, false)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        rt_allocate(layout, true)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { TcMalloc.dealloc(ptr.as_ptr(), layout) };
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { rt_realloc(ptr, old_layout, new_layout, false) }
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { rt_realloc(ptr, old_layout, new_layout, true) }
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { rt_realloc(ptr, old_layout, new_layout, false) }
+    }
+}
+
+/// Shared by `allocate`/`allocate_zeroed`: routes through `TcMalloc`'s own
+/// `GlobalAlloc` impl so the handle gets the exact same fast path as the
+/// process-wide allocator.
+#[cfg(feature = "nightly")]
+#[inline]
+fn rt_allocate(layout: Layout, zeroed: bool) -> Result<NonNull<[u8]>, AllocError> {
+    if layout.size() == 0 {
+        let ptr = NonNull::new(layout.align() as *mut u8).ok_or(AllocError)?;
+        return Ok(NonNull::slice_from_raw_parts(ptr, 0));
+    }
+    let raw = unsafe {
+        if zeroed {
+            TcMalloc.alloc_zeroed(layout)
+        } else {
+            TcMalloc.alloc(layout)
+        }
+    };
+    let ptr = NonNull::new(raw).ok_or(AllocError)?;
+    Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+}
+
+/// Shared by `grow`/`grow_zeroed`/`shrink`. Checks `resize_in_place` first:
+/// when the old and new sizes map to the same size class (or the same
+/// large span), the existing allocation is reused untouched instead of
+/// allocating, copying and freeing.
+///
+/// # Safety
+///
+/// `ptr` must have been allocated by this handle for `old_layout`, and
+/// `new_layout.size()` must be nonzero (the `Allocator` trait never calls
+/// `grow`/`shrink` with a zero-sized new layout).
+#[cfg(feature = "nightly")]
+#[inline]
+unsafe fn rt_realloc(
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+    zero_new: bool,
+) -> Result<NonNull<[u8]>, AllocError> {
+    if new_layout.align() <= old_layout.align() {
+        let usable = unsafe { TcMalloc.resize_in_place(ptr.as_ptr(), old_layout, new_layout.size()) };
+        if usable > 0 {
+            if zero_new && new_layout.size() > old_layout.size() {
+                unsafe {
+                    ptr::write_bytes(
+                        ptr.as_ptr().add(old_layout.size()),
+                        0,
+                        new_layout.size() - old_layout.size(),
+                    )
+                };
+            }
+            return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+    }
+
+    let new_memory = rt_allocate(new_layout, zero_new)?;
+    let copy_size = old_layout.size().min(new_layout.size());
+    unsafe {
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new_memory.as_mut_ptr(), copy_size);
+        TcMalloc.dealloc(ptr.as_ptr(), old_layout);
+    }
+    Ok(new_memory)
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_in_place_small_class() {
+        let layout = Layout::from_size_align(1, 1).unwrap();
+        unsafe {
+            let ptr = TcMalloc.alloc(layout);
+            assert!(!ptr.is_null());
+
+            let class_size = size_class::class_to_size(size_class::size_to_class(1));
+            // Growing within the same size class is free -- every object in
+            // a class already occupies the full class size.
+            assert_eq!(TcMalloc.resize_in_place(ptr, layout, class_size), class_size);
+            // Growing past the class boundary can't be satisfied in place.
+            assert_eq!(TcMalloc.resize_in_place(ptr, layout, class_size + 1), 0);
+
+            TcMalloc.dealloc(ptr, layout);
+        }
+    }
+
+    #[test]
+    fn test_resize_in_place_large_span() {
+        let layout = Layout::from_size_align(512 * 1024, 8).unwrap();
+        unsafe {
+            let ptr = TcMalloc.alloc(layout);
+            assert!(!ptr.is_null());
+
+            let span_bytes = TcMalloc.resize_in_place(ptr, layout, layout.size());
+            assert!(span_bytes >= layout.size());
+            assert_eq!(TcMalloc.resize_in_place(ptr, layout, span_bytes + PAGE_SIZE), 0);
+
+            TcMalloc.dealloc(ptr, layout);
+        }
+    }
+
+    #[test]
+    fn test_rt_allocator_vec_round_trip() {
+        // Vec::new_in exercises allocate/grow (and, via shrink_to_fit,
+        // shrink) without ever touching the #[global_allocator] path --
+        // RtAllocator is meant to be usable standalone per-collection.
+        let mut v: Vec<u64, RtAllocator> = Vec::new_in(RtAllocator);
+        for i in 0..2000u64 {
+            v.push(i);
+        }
+        assert_eq!(v.len(), 2000);
+        assert_eq!(v[1000], 1000);
+
+        v.shrink_to_fit();
+        assert_eq!(v.len(), 2000);
+        assert_eq!(v[500], 500);
+
+        drop(v);
+    }
+
+    #[test]
+    fn test_rt_allocator_allocate_zeroed() {
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let mem = RtAllocator.allocate_zeroed(layout).unwrap();
+        let bytes = unsafe { mem.as_ref() };
+        assert!(bytes.iter().all(|&b| b == 0));
+        let raw = NonNull::new(mem.as_ptr() as *mut u8).unwrap();
+        unsafe { RtAllocator.deallocate(raw, layout) };
+    }
+
+    #[test]
+    fn test_alloc_dealloc_batch_round_trip() {
+        unsafe {
+            let (count, head) = TcMalloc.alloc_batch(4, 64); // class 4
+            assert!(count > 0);
+            assert!(!head.is_null());
+            TcMalloc.dealloc_batch(head, count, 4);
+        }
+    }
+
+    #[test]
+    fn test_alloc_dealloc_batch_reuse() {
+        // A batch freed back should be available for a later batch alloc of
+        // the same class, the same way single alloc/free cycles reuse.
+        unsafe {
+            let (count, head) = TcMalloc.alloc_batch(4, 32);
+            assert!(count > 0);
+            TcMalloc.dealloc_batch(head, count, 4);
+
+            let (count2, head2) = TcMalloc.alloc_batch(4, 32);
+            assert!(count2 > 0);
+            assert!(!head2.is_null());
+            TcMalloc.dealloc_batch(head2, count2, 4);
+        }
+    }
+
+    #[test]
+    fn test_alloc_small_sized_round_trip() {
+        unsafe {
+            // Fits a small class.
+            let layout = Layout::from_size_align(20, 1).unwrap();
+            let ptr = TcMalloc.alloc(layout);
+            assert!(!ptr.is_null());
+            TcMalloc.dealloc(ptr, layout);
+
+            // Larger than the biggest small class: must still come back via
+            // the large path, not null.
+            let big_layout = Layout::from_size_align(size_class::MAX_SMALL_SIZE + 1, 1).unwrap();
+            let big_ptr = TcMalloc.alloc(big_layout);
+            assert!(!big_ptr.is_null());
+            TcMalloc.dealloc(big_ptr, big_layout);
+        }
+    }
+}
@@ -48,6 +48,40 @@ pub unsafe extern "C" fn rtmalloc_alloc(size: usize, align: usize) -> *mut u8 {
     unsafe { ALLOC.alloc(layout) }
 }
 
+#[cfg_attr(not(feature = "testing"), unsafe(no_mangle))]
+#[cfg_attr(
+    all(feature = "testing", feature = "percpu"),
+    unsafe(export_name = "rtmalloc_percpu_alloc_zeroed")
+)]
+#[cfg_attr(
+    all(feature = "testing", feature = "nightly", not(feature = "percpu")),
+    unsafe(export_name = "rtmalloc_nightly_alloc_zeroed")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        feature = "std",
+        not(any(feature = "nightly", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_std_alloc_zeroed")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        not(any(feature = "nightly", feature = "std", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_nostd_alloc_zeroed")
+)]
+/// # Safety
+///
+/// `align` must be a power of two. `size` must be a multiple of `align` or zero.
+/// Takes the allocator's zero-page fast path instead of always memsetting;
+/// used by the `libc-shim` `calloc` export.
+pub unsafe extern "C" fn rtmalloc_alloc_zeroed(size: usize, align: usize) -> *mut u8 {
+    let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
+    unsafe { ALLOC.alloc_zeroed(layout) }
+}
+
 #[cfg_attr(not(feature = "testing"), unsafe(no_mangle))]
 #[cfg_attr(
     all(feature = "testing", feature = "percpu"),
@@ -116,3 +150,301 @@ pub unsafe extern "C" fn rtmalloc_realloc(
     let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
     unsafe { ALLOC.realloc(ptr, layout, new_size) }
 }
+
+/// Flattened, FFI-safe totals from [`crate::stats::MemoryStats`]. The
+/// per-size-class breakdown stays Rust-only (via [`crate::stats`]); C callers
+/// get the aggregate numbers tcmalloc's `MallocExtension` exposes.
+#[repr(C)]
+pub struct RtMallocStats {
+    pub live_bytes: u64,
+    pub thread_cache_bytes: u64,
+    pub central_cache_bytes: u64,
+    pub transfer_cache_bytes: u64,
+    pub mapped_bytes: u64,
+    pub free_bytes: u64,
+}
+
+impl From<crate::stats::MemoryStats> for RtMallocStats {
+    fn from(stats: crate::stats::MemoryStats) -> Self {
+        let live_bytes = stats
+            .alloc_stats
+            .classes
+            .iter()
+            .map(|c| c.live_bytes)
+            .sum::<u64>()
+            + stats.alloc_stats.live_large_bytes;
+        Self {
+            live_bytes,
+            thread_cache_bytes: stats.thread_cache_bytes,
+            central_cache_bytes: stats.central_cache_bytes,
+            transfer_cache_bytes: stats.transfer_cache_bytes,
+            mapped_bytes: stats.mapped_bytes,
+            free_bytes: stats.free_bytes,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "testing"), unsafe(no_mangle))]
+#[cfg_attr(
+    all(feature = "testing", feature = "percpu"),
+    unsafe(export_name = "rtmalloc_percpu_get_stats")
+)]
+#[cfg_attr(
+    all(feature = "testing", feature = "nightly", not(feature = "percpu")),
+    unsafe(export_name = "rtmalloc_nightly_get_stats")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        feature = "std",
+        not(any(feature = "nightly", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_std_get_stats")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        not(any(feature = "nightly", feature = "std", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_nostd_get_stats")
+)]
+/// # Safety
+///
+/// `out` must be a valid, writable pointer to an `RtMallocStats`, or null (in
+/// which case this is a no-op).
+pub unsafe extern "C" fn rtmalloc_get_stats(out: *mut RtMallocStats) {
+    if out.is_null() {
+        return;
+    }
+    let stats = ALLOC.memory_stats();
+    unsafe { out.write(stats.into()) };
+}
+
+#[cfg_attr(not(feature = "testing"), unsafe(no_mangle))]
+#[cfg_attr(
+    all(feature = "testing", feature = "percpu"),
+    unsafe(export_name = "rtmalloc_percpu_release_memory")
+)]
+#[cfg_attr(
+    all(feature = "testing", feature = "nightly", not(feature = "percpu")),
+    unsafe(export_name = "rtmalloc_nightly_release_memory")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        feature = "std",
+        not(any(feature = "nightly", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_std_release_memory")
+)]
+#[cfg_attr(
+    all(
+        feature = "testing",
+        not(any(feature = "nightly", feature = "std", feature = "percpu"))
+    ),
+    unsafe(export_name = "rtmalloc_nostd_release_memory")
+)]
+/// Decommit every free span currently held by the page heap, shrinking RSS
+/// without giving back address space. Returns the number of bytes
+/// decommitted.
+pub extern "C" fn rtmalloc_release_memory() -> usize {
+    ALLOC.release_memory()
+}
+
+/// Drop-in libc malloc replacement: `malloc`/`free`/`calloc`/`realloc`/
+/// `posix_memalign`/`aligned_alloc`/`memalign`/`malloc_usable_size`, exported
+/// under their plain libc names so the staticlib can be `LD_PRELOAD`ed or
+/// linked in place of the system allocator.
+///
+/// Unlike the `rtmalloc_*` exports above, libc's `free`/`realloc`/
+/// `malloc_usable_size` receive no size or layout from the caller. We
+/// recover it by looking the pointer's page up in the page map, the same
+/// mechanism `TcMalloc::dealloc_slow` already uses internally.
+///
+/// Gated behind `libc-shim` so these plain names don't collide with the
+/// `rtmalloc_*` exports when both features are enabled in the same build.
+#[cfg(feature = "libc-shim")]
+mod libc_shim {
+    use crate::allocator::TcMalloc;
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::ptr;
+
+    /// Alignment libc's plain `malloc`/`calloc` guarantee: two words on
+    /// 64-bit, matching `max_align_t`.
+    const DEFAULT_ALIGN: usize = 2 * core::mem::size_of::<usize>();
+
+    const EINVAL: i32 = 22;
+    const ENOMEM: i32 = 12;
+
+    /// # Safety
+    ///
+    /// Standard libc `malloc` contract.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn malloc(size: usize) -> *mut u8 {
+        if size == 0 {
+            return ptr::null_mut();
+        }
+        let layout = unsafe { Layout::from_size_align_unchecked(size, DEFAULT_ALIGN) };
+        unsafe { TcMalloc.alloc(layout) }
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must be null or have been returned by `malloc`/`calloc`/`realloc`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn free(ptr: *mut u8) {
+        unsafe { TcMalloc.dealloc_unsized(ptr) };
+    }
+
+    /// # Safety
+    ///
+    /// Standard libc `calloc` contract.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn calloc(nmemb: usize, size: usize) -> *mut u8 {
+        let total = match nmemb.checked_mul(size) {
+            Some(total) if total > 0 => total,
+            _ => return ptr::null_mut(),
+        };
+        unsafe { super::rtmalloc_alloc_zeroed(total, DEFAULT_ALIGN) }
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must be null or have been returned by `malloc`/`calloc`/`realloc`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn realloc(ptr: *mut u8, new_size: usize) -> *mut u8 {
+        if ptr.is_null() {
+            return unsafe { malloc(new_size) };
+        }
+        if new_size == 0 {
+            unsafe { free(ptr) };
+            return ptr::null_mut();
+        }
+        let old_size = TcMalloc.usable_size(ptr);
+        let old_layout = unsafe { Layout::from_size_align_unchecked(old_size, DEFAULT_ALIGN) };
+        unsafe { TcMalloc.realloc(ptr, old_layout, new_size) }
+    }
+
+    /// # Safety
+    ///
+    /// Standard libc `posix_memalign` contract: `align` must be a power of
+    /// two that is also a multiple of `size_of::<*const ()>()`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn posix_memalign(
+        memptr: *mut *mut u8,
+        align: usize,
+        size: usize,
+    ) -> i32 {
+        if size == 0 {
+            unsafe { *memptr = ptr::null_mut() };
+            return 0;
+        }
+        let layout = match Layout::from_size_align(size, align) {
+            Ok(layout) => layout,
+            Err(_) => return EINVAL,
+        };
+        let p = unsafe { TcMalloc.alloc(layout) };
+        if p.is_null() {
+            return ENOMEM;
+        }
+        unsafe { *memptr = p };
+        0
+    }
+
+    /// # Safety
+    ///
+    /// Standard libc `aligned_alloc` contract: `align` must be a power of two
+    /// and `size` a multiple of `align`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn aligned_alloc(align: usize, size: usize) -> *mut u8 {
+        let layout = match Layout::from_size_align(size, align) {
+            Ok(layout) => layout,
+            Err(_) => return ptr::null_mut(),
+        };
+        unsafe { TcMalloc.alloc(layout) }
+    }
+
+    /// # Safety
+    ///
+    /// Legacy alias for `aligned_alloc`, same contract.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn memalign(align: usize, size: usize) -> *mut u8 {
+        unsafe { aligned_alloc(align, size) }
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must be null or have been returned by this module's allocation
+    /// functions.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn malloc_usable_size(ptr: *mut u8) -> usize {
+        if ptr.is_null() {
+            return 0;
+        }
+        TcMalloc.usable_size(ptr)
+    }
+}
+
+/// Weak `__rust_alloc*` symbols per RFC 1974's allocator ABI.
+///
+/// `liballoc` calls these four symbols for every allocation when no
+/// `#[global_allocator]` is registered; the standard library provides weak
+/// default definitions (backed by the system allocator) that a crate
+/// providing its own are free to override by exporting strong symbols with
+/// the same names. Gated behind `rust-shim` so a cdylib/staticlib built from
+/// this crate can satisfy `liballoc`'s requirement directly -- useful when
+/// the consumer links this crate into a build that has no Rust
+/// `#[global_allocator]` static at all (e.g. a C host program, or a
+/// `no_std` binary assembling its allocator purely from linked objects).
+#[cfg(feature = "rust-shim")]
+mod rust_shim {
+    use crate::allocator::TcMalloc;
+    use core::alloc::{GlobalAlloc, Layout};
+
+    /// # Safety
+    ///
+    /// `align` must be a power of two and `size`, rounded up to `align`,
+    /// must not overflow `isize`, matching `GlobalAlloc::alloc`'s contract.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn __rust_alloc(size: usize, align: usize) -> *mut u8 {
+        let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
+        unsafe { TcMalloc.alloc(layout) }
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `__rust_alloc`/`__rust_realloc` with
+    /// the same `size`/`align`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn __rust_dealloc(ptr: *mut u8, size: usize, align: usize) {
+        let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
+        unsafe { TcMalloc.dealloc(ptr, layout) };
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `__rust_alloc`/`__rust_realloc` with
+    /// the given `old_size`/`align`. Preserves the in-place same-size-class
+    /// optimization: `GlobalAlloc::realloc` already returns `ptr` unchanged
+    /// whenever `new_size` still maps to the original size class.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn __rust_realloc(
+        ptr: *mut u8,
+        old_size: usize,
+        align: usize,
+        new_size: usize,
+    ) -> *mut u8 {
+        let layout = unsafe { Layout::from_size_align_unchecked(old_size, align) };
+        unsafe { TcMalloc.realloc(ptr, layout, new_size) }
+    }
+
+    /// # Safety
+    ///
+    /// Same contract as `__rust_alloc`. Uses the zero-page fast path instead
+    /// of always memsetting, same as `rtmalloc_alloc_zeroed`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn __rust_alloc_zeroed(size: usize, align: usize) -> *mut u8 {
+        let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
+        unsafe { TcMalloc.alloc_zeroed(layout) }
+    }
+}
@@ -0,0 +1,56 @@
+//! Optional hardening: zero freed small objects and detect write-after-free.
+//!
+//! Enabled via the `hardened` cargo feature. Every small object is zeroed
+//! (apart from the first word, which the free list overwrites with its
+//! intrusive `next` pointer on the very next push) before it re-enters a free
+//! list. On the next allocation from that slot we scan the rest of the
+//! payload and abort if anything is non-zero -- that can only happen if
+//! something wrote into the object after it was freed. Non-hardened builds
+//! never call into this module, so they pay nothing for it.
+//!
+//! When the `canary` feature is also enabled, the second word is excluded
+//! too: that's where `thread_cache`'s guard word lives, and it is
+//! intentionally nonzero, not a write-after-free.
+
+use core::mem::size_of;
+use core::ptr;
+
+/// Pointer-sized words at the head of a freed object that belong to the free
+/// list itself and must never be scrubbed or verified by this module: the
+/// intrusive `next` pointer, plus -- when the `canary` feature is also
+/// enabled -- the guard word `thread_cache` writes right after it. Without
+/// excluding the second word too, `hardened` and `canary` fight over the
+/// same byte range: `canary` needs to leave a nonzero guard there on every
+/// `push`, `hardened` requires everything past `next` to be zero, and the
+/// very first alloc/free/alloc cycle with both features enabled aborts.
+#[cfg(feature = "canary")]
+const HEADER_WORDS: usize = 2;
+#[cfg(not(feature = "canary"))]
+const HEADER_WORDS: usize = 1;
+
+/// Zero everything but the leading header words, which the free list is
+/// about to overwrite with its own `next` link (and, under `canary`, its
+/// guard word).
+#[inline]
+pub unsafe fn scrub_on_free(ptr: *mut u8, class_size: usize) {
+    let header = size_of::<usize>() * HEADER_WORDS;
+    if class_size > header {
+        unsafe { ptr::write_bytes(ptr.add(header), 0, class_size - header) };
+    }
+}
+
+/// Verify the object is still all-zero past the leading header words. Aborts
+/// the process if a write-after-free is detected.
+#[inline]
+pub unsafe fn verify_on_alloc(ptr: *mut u8, class_size: usize) {
+    let header = size_of::<usize>() * HEADER_WORDS;
+    if class_size <= header {
+        return;
+    }
+    let payload = unsafe { core::slice::from_raw_parts(ptr.add(header), class_size - header) };
+    if payload.iter().any(|&b| b != 0) {
+        // The object was written to after it was freed; this is a corruption
+        // bug in the caller, not something we can recover from safely.
+        unsafe { core::intrinsics::abort() };
+    }
+}
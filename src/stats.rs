@@ -0,0 +1,217 @@
+//! Opt-in per-size-class allocation statistics.
+//!
+//! Every counter is a relaxed atomic so the fast paths in `alloc_small`/
+//! `dealloc_small` stay lock-free; callers needing a consistent view can
+//! call [`stats`] for a `Copy` snapshot. Zero-byte requests consume no
+//! memory but are still counted, mirroring hardened_malloc's treatment of
+//! the zero-size sentinel allocation.
+
+use crate::size_class::{self, NUM_SIZE_CLASSES};
+use crate::PAGE_SIZE;
+use core::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+struct ClassCounters {
+    allocs: AtomicU64,
+    frees: AtomicU64,
+}
+
+impl ClassCounters {
+    const fn new() -> Self {
+        Self {
+            allocs: AtomicU64::new(0),
+            frees: AtomicU64::new(0),
+        }
+    }
+}
+
+static CLASS_COUNTERS: [ClassCounters; NUM_SIZE_CLASSES] =
+    [const { ClassCounters::new() }; NUM_SIZE_CLASSES];
+
+static LARGE_ALLOCS: AtomicU64 = AtomicU64::new(0);
+static LARGE_FREES: AtomicU64 = AtomicU64::new(0);
+static ZERO_SIZE_ALLOCS: AtomicU64 = AtomicU64::new(0);
+
+/// Running total of pages backing every currently-live large (page-heap)
+/// allocation. Large spans are frequently several pages, so this is tracked
+/// directly rather than derived from `live_large_spans * PAGE_SIZE`, which
+/// would silently undercount anything bigger than one page. Signed for the
+/// same reason as `THREAD_CACHE_BYTES`: concurrent increments/decrements
+/// from different threads must never underflow into a bogus wraparound.
+static LARGE_LIVE_PAGES: AtomicI64 = AtomicI64::new(0);
+
+/// Running total of bytes held across every thread's thread cache.
+///
+/// There is no global registry of live `ThreadCache`s yet, so this is kept
+/// up to date incrementally: every place `thread_cache::ThreadCache` grows
+/// or shrinks its own `total_size` reports the delta here instead. Signed so
+/// concurrent increments/decrements from different threads can never
+/// underflow into a bogus wraparound the way an unsigned counter would.
+static THREAD_CACHE_BYTES: AtomicI64 = AtomicI64::new(0);
+
+/// Record a small allocation of the given size class.
+#[inline]
+pub fn record_small_alloc(class: usize) {
+    CLASS_COUNTERS[class].allocs.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record a small deallocation of the given size class.
+#[inline]
+pub fn record_small_free(class: usize) {
+    CLASS_COUNTERS[class].frees.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record a large (page-heap) allocation of `pages` pages.
+#[inline]
+pub fn record_large_alloc(pages: usize) {
+    LARGE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+    LARGE_LIVE_PAGES.fetch_add(pages as i64, Ordering::Relaxed);
+}
+
+/// Record a large (page-heap) deallocation of `pages` pages.
+#[inline]
+pub fn record_large_free(pages: usize) {
+    LARGE_FREES.fetch_add(1, Ordering::Relaxed);
+    LARGE_LIVE_PAGES.fetch_sub(pages as i64, Ordering::Relaxed);
+}
+
+/// Adjust the live-page total by `delta` without touching the alloc/free
+/// counters, for a span that is resized in place (e.g. via `mremap`) rather
+/// than freed and reallocated.
+#[inline]
+pub fn adjust_large_live_pages(delta: i64) {
+    LARGE_LIVE_PAGES.fetch_add(delta, Ordering::Relaxed);
+}
+
+/// Record a zero-size allocation request (no memory is actually handed out).
+#[inline]
+pub fn record_zero_size_alloc() {
+    ZERO_SIZE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Report a change in how many bytes a thread cache is holding. `delta` is
+/// positive when the cache grows (a batch refill, a freed object) and
+/// negative when it shrinks (an allocation served locally, a spill to
+/// central).
+#[inline]
+pub fn add_thread_cache_bytes(delta: i64) {
+    THREAD_CACHE_BYTES.fetch_add(delta, Ordering::Relaxed);
+}
+
+/// Current best-effort total of bytes cached across all thread caches.
+#[inline]
+pub fn thread_cache_bytes() -> u64 {
+    THREAD_CACHE_BYTES.load(Ordering::Relaxed).max(0) as u64
+}
+
+/// Snapshot of cumulative and live counters for a single size class.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClassStats {
+    /// Size class this snapshot describes.
+    pub size_class: usize,
+    /// Cumulative allocations of this class.
+    pub allocs: u64,
+    /// Cumulative frees of this class.
+    pub frees: u64,
+    /// Objects currently live (`allocs - frees`).
+    pub live_objects: u64,
+    /// Bytes currently live (`live_objects * class size`).
+    pub live_bytes: u64,
+}
+
+/// `Copy` snapshot of the whole statistics subsystem.
+#[derive(Clone, Copy, Debug)]
+pub struct AllocStats {
+    /// Per-size-class counters, indexed the same as `SIZE_CLASSES` (index 0 unused).
+    pub classes: [ClassStats; NUM_SIZE_CLASSES],
+    /// Cumulative large (page-heap) allocations.
+    pub large_allocs: u64,
+    /// Cumulative large (page-heap) frees.
+    pub large_frees: u64,
+    /// Live large spans (`large_allocs - large_frees`).
+    pub live_large_spans: u64,
+    /// Bytes currently live across every large span, tracked directly from
+    /// each span's real page count rather than assumed to be one page each.
+    pub live_large_bytes: u64,
+    /// Cumulative zero-size allocation requests.
+    pub zero_size_allocs: u64,
+}
+
+/// Take a consistent-enough snapshot of all allocation statistics.
+///
+/// Each counter is read independently with `Ordering::Relaxed`, so under
+/// concurrent traffic the snapshot may not be perfectly point-in-time
+/// consistent across classes -- acceptable for diagnosing fragmentation and
+/// leaks, which is the intended use.
+pub fn stats() -> AllocStats {
+    let mut classes = [ClassStats::default(); NUM_SIZE_CLASSES];
+    for (cls, counters) in classes.iter_mut().enumerate().skip(1) {
+        let allocs = CLASS_COUNTERS[cls].allocs.load(Ordering::Relaxed);
+        let frees = CLASS_COUNTERS[cls].frees.load(Ordering::Relaxed);
+        let live_objects = allocs.saturating_sub(frees);
+        *counters = ClassStats {
+            size_class: cls,
+            allocs,
+            frees,
+            live_objects,
+            live_bytes: live_objects * size_class::class_to_size(cls) as u64,
+        };
+    }
+
+    let large_allocs = LARGE_ALLOCS.load(Ordering::Relaxed);
+    let large_frees = LARGE_FREES.load(Ordering::Relaxed);
+
+    AllocStats {
+        classes,
+        large_allocs,
+        large_frees,
+        live_large_spans: large_allocs.saturating_sub(large_frees),
+        live_large_bytes: LARGE_LIVE_PAGES.load(Ordering::Relaxed).max(0) as u64 * PAGE_SIZE as u64,
+        zero_size_allocs: ZERO_SIZE_ALLOCS.load(Ordering::Relaxed),
+    }
+}
+
+/// Full memory-introspection snapshot, modeled on tcmalloc's
+/// `MallocExtension`: per-class allocation counters plus how many bytes are
+/// sitting idle at each cache tier and how much has been mapped in from the
+/// OS overall. Built by `allocator::memory_stats`, which is the only caller
+/// with access to `CentralCache`/`TransferCacheArray`/`PageHeap` to read the
+/// tier byte counts from.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryStats {
+    /// Per-size-class allocation counters and live bytes.
+    pub alloc_stats: AllocStats,
+    /// Bytes idle in thread caches (best-effort, see `THREAD_CACHE_BYTES`).
+    pub thread_cache_bytes: u64,
+    /// Bytes idle in the central free lists.
+    pub central_cache_bytes: u64,
+    /// Bytes idle in the transfer cache (nightly builds only; 0 otherwise).
+    pub transfer_cache_bytes: u64,
+    /// Bytes currently mapped in from the OS across all spans, live or free.
+    pub mapped_bytes: u64,
+    /// Bytes mapped but not backing any live allocation (free spans plus
+    /// everything cached in the three tiers above).
+    pub free_bytes: u64,
+}
+
+/// Assemble a [`MemoryStats`] snapshot from the per-class counters this
+/// module already tracks plus the tier byte counts the caller read from the
+/// allocator's cache statics.
+pub fn build_memory_stats(
+    central_cache_bytes: u64,
+    transfer_cache_bytes: u64,
+    mapped_bytes: u64,
+    page_heap_free_bytes: u64,
+) -> MemoryStats {
+    let thread_cache_bytes = thread_cache_bytes();
+    MemoryStats {
+        alloc_stats: stats(),
+        thread_cache_bytes,
+        central_cache_bytes,
+        transfer_cache_bytes,
+        mapped_bytes,
+        free_bytes: page_heap_free_bytes
+            + thread_cache_bytes
+            + central_cache_bytes
+            + transfer_cache_bytes,
+    }
+}
@@ -3,6 +3,13 @@
 //! Each thread gets its own ThreadCache via `thread_local!`. The fast path
 //! (thread cache hit) requires zero synchronization. When the thread cache
 //! is empty or full, it batches transfers to/from the central free list.
+//!
+//! With the `canary` feature, every free list additionally writes a guard
+//! word alongside each object's intrusive `next` pointer and verifies it on
+//! pop, aborting the process on a mismatch -- catching double-frees and
+//! write-after-free corruption before they can be exploited. Objects smaller
+//! than two pointer words (see `CANARY_MIN_OBJECT_SIZE`) have no room for a
+//! guard and are left unprotected.
 
 use crate::central_free_list::CentralCache;
 use crate::page_heap::PageHeap;
@@ -10,15 +17,70 @@ use crate::pagemap::PageMap;
 use crate::size_class::{self, NUM_SIZE_CLASSES};
 use crate::span::FreeObject;
 use crate::sync::SpinMutex;
+#[cfg(feature = "canary")]
+use core::mem::size_of;
 use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// Maximum total bytes a thread cache can hold before triggering GC.
 const MAX_THREAD_CACHE_SIZE: usize = 4 * 1024 * 1024; // 4 MiB
 
 /// Minimum total bytes a thread cache keeps (floor for shrinking).
-#[allow(dead_code)]
 const MIN_THREAD_CACHE_SIZE: usize = 512 * 1024; // 512 KiB
 
+/// Deallocations between periodic low-water-mark scavenges (see
+/// `ThreadCache::scavenge`).
+const SCAVENGE_INTERVAL: u32 = 64;
+
+/// Ceiling on `FreeList::max_length`'s slow-start growth, mirroring
+/// tcmalloc's `kMaxDynamicFreeListLength`.
+const MAX_DYNAMIC_FREE_LIST_LENGTH: u32 = 8192;
+
+/// Combined byte budget shared by every registered thread cache, before
+/// `set_overall_cache_size` overrides it.
+const DEFAULT_OVERALL_CACHE_SIZE: usize = 32 * 1024 * 1024; // 32 MiB
+
+/// Underflows (see `fetch_from_central`) between growth attempts -- mirrors
+/// `SCAVENGE_INTERVAL`'s role for the shrink side.
+const GROWTH_CHECK_INTERVAL: u32 = 32;
+
+/// Bytes moved between two thread caches by a single steal or grow step.
+const STEAL_CHUNK: usize = 256 * 1024; // 256 KiB
+
+/// Smallest object size the `canary` feature can protect: a guard word sits
+/// right after `next`, so the object needs two pointer-sized words of room.
+/// Classes smaller than this (the 8-byte class on a 64-bit target) are left
+/// unguarded -- the request that motivated this is double-free/write-after-
+/// free detection, not crashing on the very smallest allocations, and doing
+/// that properly would need a side metadata bit rather than inline storage.
+#[cfg(feature = "canary")]
+const CANARY_MIN_OBJECT_SIZE: usize = 2 * size_of::<usize>();
+
+/// Write the guard word for `obj` (whose intrusive `next` is already set to
+/// `next`) into the pointer-sized slot right after `next`.
+#[cfg(feature = "canary")]
+#[inline]
+unsafe fn write_canary(obj: *mut FreeObject, next: *mut FreeObject, secret: usize) {
+    let guard = (next as usize) ^ secret ^ (obj as usize);
+    let header = size_of::<*mut FreeObject>();
+    unsafe { (obj as *mut u8).add(header).cast::<usize>().write(guard) };
+}
+
+/// Recompute and verify the guard word for `obj`, whose intrusive `next` has
+/// already been read as `next`. Aborts the process on mismatch: either
+/// `next` itself or the guard slot was corrupted by a write into memory that
+/// is supposed to be free, which is not something safe to recover from.
+#[cfg(feature = "canary")]
+#[inline]
+unsafe fn verify_canary(obj: *mut FreeObject, next: *mut FreeObject, secret: usize) {
+    let header = size_of::<*mut FreeObject>();
+    let stored = unsafe { (obj as *mut u8).add(header).cast::<usize>().read() };
+    let expected = (next as usize) ^ secret ^ (obj as usize);
+    if stored != expected {
+        unsafe { core::intrinsics::abort() };
+    }
+}
+
 /// Per-size-class free list within the thread cache.
 struct FreeList {
     /// Head of the singly-linked intrusive free list.
@@ -27,6 +89,27 @@ struct FreeList {
     length: u32,
     /// Maximum length before we return objects to central cache.
     max_length: u32,
+    /// Lowest `length` observed since the last scavenge reset this window.
+    /// Objects that survive a whole window without being popped are the
+    /// coldest ones in the list, so `lowater` is exactly how many are safe
+    /// to evict without touching the working set.
+    lowater: u32,
+    /// Set whenever a fetch from central happens because this list
+    /// underflowed (see `fetch_from_central`), cleared the next time
+    /// `release_to_central` checks it. Lets the overflow path tell "this
+    /// class is in steady churn, don't shrink its batch" apart from "this
+    /// class overflowed once and has been idle since, shrink it back down".
+    recently_underflowed: bool,
+    /// True if every object currently chained into this list is known-zero:
+    /// carved from a span fresh from the OS and never recycled through a
+    /// free list. Sticky across `pop`, cleared by any `push`/`push_batch`
+    /// that isn't itself reporting a fresh batch.
+    fresh: bool,
+    /// XOR secret mixed into every guard word this list writes (`canary`
+    /// feature only). Lazily seeded on first use since `FreeList::new` must
+    /// stay `const`; 0 is the "unseeded" sentinel, forced nonzero once set.
+    #[cfg(feature = "canary")]
+    canary_secret: usize,
 }
 
 impl FreeList {
@@ -35,55 +118,145 @@ impl FreeList {
             head: ptr::null_mut(),
             length: 0,
             max_length: 1, // Start small, grows adaptively
+            lowater: 0,
+            recently_underflowed: false,
+            fresh: false,
+            #[cfg(feature = "canary")]
+            canary_secret: 0,
+        }
+    }
+
+    /// Lazily seed (if needed) and return this list's guard secret. Seeded
+    /// from the list's own address mixed with a constant -- cheap, needs no
+    /// RNG state, and differs per size class since every `FreeList` lives at
+    /// a distinct address.
+    #[cfg(feature = "canary")]
+    #[inline]
+    fn canary_secret(&mut self) -> usize {
+        if self.canary_secret == 0 {
+            let addr = self as *mut Self as usize;
+            self.canary_secret = (addr ^ 0x9E37_79B9_7F4A_7C15) | 1;
         }
+        self.canary_secret
     }
 
+    /// Pop the head object. `obj_size` is this list's class size; objects
+    /// smaller than `CANARY_MIN_OBJECT_SIZE` never had a guard written (see
+    /// `push`) so are popped without a check.
     #[inline]
-    fn pop(&mut self) -> *mut FreeObject {
+    fn pop(&mut self, #[cfg_attr(not(feature = "canary"), allow(unused_variables))] obj_size: usize) -> *mut FreeObject {
         let obj = self.head;
         if !obj.is_null() {
-            self.head = unsafe { (*obj).next };
+            let next = unsafe { (*obj).next };
+            #[cfg(feature = "canary")]
+            if obj_size >= CANARY_MIN_OBJECT_SIZE {
+                let secret = self.canary_secret();
+                unsafe { verify_canary(obj, next, secret) };
+            }
+            self.head = next;
             self.length -= 1;
+            self.lowater = self.lowater.min(self.length);
         }
         obj
     }
 
+    /// Push `obj` (this list's class size is `obj_size`) back onto the head.
+    ///
+    /// With the `canary` feature, also catches the cheapest double-free
+    /// signature -- the same pointer freed twice with nothing popped in
+    /// between, which would otherwise silently turn the list into a cycle --
+    /// and (for objects with room) writes a guard word alongside `next` so a
+    /// later `pop` can detect `next` (or the guard itself) having been
+    /// corrupted by a write into memory that's supposed to be free.
     #[inline]
-    fn push(&mut self, obj: *mut FreeObject) {
+    fn push(&mut self, obj: *mut FreeObject, #[cfg_attr(not(feature = "canary"), allow(unused_variables))] obj_size: usize) {
+        #[cfg(feature = "canary")]
+        {
+            if obj == self.head {
+                unsafe { core::intrinsics::abort() };
+            }
+            if obj_size >= CANARY_MIN_OBJECT_SIZE {
+                let secret = self.canary_secret();
+                unsafe { write_canary(obj, self.head, secret) };
+            }
+        }
         unsafe { (*obj).next = self.head };
         self.head = obj;
         self.length += 1;
+        self.fresh = false;
     }
 
-    /// Push a linked list of `count` objects.
-    fn push_batch(&mut self, head: *mut FreeObject, count: u32) {
+    /// Push a linked list of `count` objects fetched from central, marking
+    /// whether they are known-zero (carved from a span never previously
+    /// recycled). The list must be empty when this is called -- it is only
+    /// reached from `fetch_from_central` right after an empty `pop`.
+    ///
+    /// With `canary`, every node in the batch gets a guard written, not just
+    /// ones pushed individually -- otherwise the next single `pop` of a
+    /// batch-fetched object would find an unwritten guard slot and abort on
+    /// what is actually a false positive.
+    fn push_batch(
+        &mut self,
+        head: *mut FreeObject,
+        count: u32,
+        fresh: bool,
+        #[cfg_attr(not(feature = "canary"), allow(unused_variables))] obj_size: usize,
+    ) {
         if head.is_null() || count == 0 {
             return;
         }
-        // Find the tail of the batch
+        #[cfg(feature = "canary")]
+        let guard_all = obj_size >= CANARY_MIN_OBJECT_SIZE;
+        #[cfg(feature = "canary")]
+        let secret = if guard_all { self.canary_secret() } else { 0 };
+
+        // Find the tail of the batch, guarding each node as we pass it --
+        // every node's `next` is already final except the tail's, patched
+        // below once we know what it points to.
         let mut tail = head;
         for _ in 1..count {
             let next = unsafe { (*tail).next };
             if next.is_null() {
                 break;
             }
+            #[cfg(feature = "canary")]
+            if guard_all {
+                unsafe { write_canary(tail, next, secret) };
+            }
             tail = next;
         }
         unsafe { (*tail).next = self.head };
+        #[cfg(feature = "canary")]
+        if guard_all {
+            unsafe { write_canary(tail, self.head, secret) };
+        }
         self.head = head;
         self.length += count;
+        self.fresh = fresh;
     }
 
     /// Pop up to `count` objects into a linked list. Returns (actual_count, head).
-    fn pop_batch(&mut self, count: u32) -> (u32, *mut FreeObject) {
+    fn pop_batch(
+        &mut self,
+        count: u32,
+        #[cfg_attr(not(feature = "canary"), allow(unused_variables))] obj_size: usize,
+    ) -> (u32, *mut FreeObject) {
         let mut head: *mut FreeObject = ptr::null_mut();
         let mut popped = 0u32;
+        #[cfg(feature = "canary")]
+        let guard_all = obj_size >= CANARY_MIN_OBJECT_SIZE && self.canary_secret != 0;
         while popped < count && !self.head.is_null() {
             let obj = self.head;
-            self.head = unsafe { (*obj).next };
+            let next = unsafe { (*obj).next };
+            #[cfg(feature = "canary")]
+            if guard_all {
+                unsafe { verify_canary(obj, next, self.canary_secret) };
+            }
+            self.head = next;
             unsafe { (*obj).next = head };
             head = obj;
             self.length -= 1;
+            self.lowater = self.lowater.min(self.length);
             popped += 1;
         }
         (popped, head)
@@ -93,18 +266,35 @@ impl FreeList {
 /// Per-thread cache holding free lists for each size class.
 pub struct ThreadCache {
     lists: [FreeList; NUM_SIZE_CLASSES],
-    /// Total bytes cached across all size classes.
-    total_size: usize,
-    /// Per-thread cache size limit.
-    max_size: usize,
+    /// Total bytes cached across all size classes. Atomic because
+    /// `ThreadCacheManager` reads other threads' totals (under `MANAGER`'s
+    /// lock) when looking for an idle donor to steal from; only the owning
+    /// thread ever writes it, always with `Ordering::Relaxed`.
+    total_size: AtomicUsize,
+    /// Per-thread cache size limit. Atomic because `ThreadCacheManager` can
+    /// shrink or grow it from another thread when rebalancing the shared
+    /// budget (see `manager::maybe_steal`/`maybe_grow`).
+    max_size: AtomicUsize,
+    /// Deallocations since the last periodic scavenge.
+    dealloc_count: u32,
+    /// Underflows (central-cache fetches) since the last growth attempt.
+    underflow_count: u32,
+    /// Intrusive links for `ThreadCacheManager`'s global registry. Null when
+    /// not registered.
+    manager_next: *mut ThreadCache,
+    manager_prev: *mut ThreadCache,
 }
 
 impl ThreadCache {
     pub fn new() -> Self {
         Self {
             lists: [const { FreeList::new() }; NUM_SIZE_CLASSES],
-            total_size: 0,
-            max_size: MAX_THREAD_CACHE_SIZE,
+            total_size: AtomicUsize::new(0),
+            max_size: AtomicUsize::new(MAX_THREAD_CACHE_SIZE),
+            dealloc_count: 0,
+            underflow_count: 0,
+            manager_next: ptr::null_mut(),
+            manager_prev: ptr::null_mut(),
         }
     }
 
@@ -118,17 +308,64 @@ impl ThreadCache {
         page_heap: &SpinMutex<PageHeap>,
         pagemap: &PageMap,
     ) -> *mut u8 {
+        let (ptr, _fresh) =
+            unsafe { self.allocate_maybe_fresh(size_class, central, page_heap, pagemap) };
+        ptr
+    }
+
+    /// Like `allocate`, but also reports whether the returned object is
+    /// known-zero: carved from a span fresh from the OS and never recycled
+    /// through a free list. `alloc_zeroed` uses this to skip the memset.
+    #[inline]
+    pub unsafe fn allocate_maybe_fresh(
+        &mut self,
+        size_class: usize,
+        central: &CentralCache,
+        page_heap: &SpinMutex<PageHeap>,
+        pagemap: &PageMap,
+    ) -> (*mut u8, bool) {
+        let obj_size = size_class::class_to_size(size_class);
         let list = &mut self.lists[size_class];
-        let obj = list.pop();
+        let fresh = list.fresh;
+        let obj = list.pop(obj_size);
         if !obj.is_null() {
-            let obj_size = size_class::class_to_size(size_class);
-            self.total_size -= obj_size;
-            return obj as *mut u8;
+            self.total_size.fetch_sub(obj_size, Ordering::Relaxed);
+            crate::stats::add_thread_cache_bytes(-(obj_size as i64));
+            return (obj as *mut u8, fresh);
         }
         // Slow path: fetch from central cache
         unsafe { self.fetch_from_central(size_class, central, page_heap, pagemap) }
     }
 
+    /// Allocate an object of at least `size` bytes, classifying internally
+    /// via [`size_class::class_index_maybe`] instead of making the caller
+    /// compute the size class first -- a single table load plus shift in
+    /// front of `lists[size_class]`, rather than the caller looking up the
+    /// class and then handing it back in a second call. Returns the class
+    /// used alongside the pointer, so callers that also need it (stats,
+    /// `hardened`) don't have to look it up a second time themselves.
+    ///
+    /// Returns `(null, 0)` both on allocation failure and when `size` is
+    /// larger than the largest small class -- callers that need to fall back
+    /// to the large-object path themselves should call
+    /// `size_class::class_index_maybe` directly instead.
+    #[inline]
+    pub unsafe fn allocate_size(
+        &mut self,
+        size: usize,
+        central: &CentralCache,
+        page_heap: &SpinMutex<PageHeap>,
+        pagemap: &PageMap,
+    ) -> (*mut u8, usize) {
+        match size_class::class_index_maybe(size) {
+            Some(size_class) => (
+                unsafe { self.allocate(size_class, central, page_heap, pagemap) },
+                size_class,
+            ),
+            None => (ptr::null_mut(), 0),
+        }
+    }
+
     /// Deallocate an object of the given size class.
     #[inline]
     pub unsafe fn deallocate(
@@ -139,25 +376,44 @@ impl ThreadCache {
         page_heap: &SpinMutex<PageHeap>,
         pagemap: &PageMap,
     ) {
+        let obj_size = size_class::class_to_size(size_class);
         let list = &mut self.lists[size_class];
         let obj = ptr as *mut FreeObject;
-        list.push(obj);
+        list.push(obj, obj_size);
 
-        let obj_size = size_class::class_to_size(size_class);
-        self.total_size += obj_size;
+        self.total_size.fetch_add(obj_size, Ordering::Relaxed);
+        crate::stats::add_thread_cache_bytes(obj_size as i64);
 
         // Check if we should return objects to central cache
         if list.length > list.max_length {
             unsafe { self.release_to_central(size_class, central, page_heap, pagemap) };
         }
 
-        // Check total cache size for GC
-        if self.total_size > self.max_size {
+        // Periodic low-water-mark scavenge, independent of any one class
+        // overflowing -- this is what actually reclaims cold memory. Also
+        // scavenge immediately if a burst of frees blew past the per-thread
+        // cap, rather than waiting out the rest of the interval.
+        self.dealloc_count += 1;
+        let total_size = self.total_size.load(Ordering::Relaxed);
+        if self.dealloc_count >= SCAVENGE_INTERVAL || total_size > self.max_size.load(Ordering::Relaxed) {
+            self.dealloc_count = 0;
             unsafe { self.scavenge(central, page_heap, pagemap) };
         }
+
+        // Shared-budget rebalancing: if every registered thread's cache
+        // combined is at the configured cap, shrink whichever one has
+        // drifted furthest above its fair share. This may be `self` (handled
+        // immediately by the check above on the very next deallocate) or
+        // another thread entirely, which will notice and scavenge itself
+        // down the next time it frees anything.
+        if manager::total_cached_bytes() >= manager::overall_cache_size() {
+            manager::maybe_steal();
+        }
     }
 
     /// Slow path: fetch a batch of objects from the central free list.
+    /// Returns the object for the caller plus whether it is known-zero (see
+    /// `allocate_maybe_fresh`).
     #[cold]
     unsafe fn fetch_from_central(
         &mut self,
@@ -165,19 +421,23 @@ impl ThreadCache {
         central: &CentralCache,
         page_heap: &SpinMutex<PageHeap>,
         pagemap: &PageMap,
-    ) -> *mut u8 {
+    ) -> (*mut u8, bool) {
         let info = size_class::class_info(size_class);
-        let batch = info.batch_size;
+        let batch_size = info.batch_size as u32;
+
+        // Slow start: pull at most `max_length` objects, not always a full
+        // batch, so classes that rarely underflow stay on small fetches.
+        let to_fetch = self.lists[size_class].max_length.min(batch_size).max(1) as usize;
 
-        let (count, head) = unsafe {
+        let (count, head, fresh) = unsafe {
             central
                 .get(size_class)
                 .lock()
-                .remove_range(batch, page_heap, pagemap)
+                .remove_range(to_fetch, page_heap, pagemap)
         };
 
         if count == 0 || head.is_null() {
-            return ptr::null_mut();
+            return (ptr::null_mut(), false);
         }
 
         // Take the first object for the caller
@@ -187,21 +447,144 @@ impl ThreadCache {
 
         // Put the rest in our thread-local free list
         if remaining_count > 0 {
-            self.lists[size_class].push_batch(remaining_head, remaining_count as u32);
-            self.total_size += remaining_count * info.size;
+            self.lists[size_class].push_batch(remaining_head, remaining_count as u32, fresh, info.size);
+            self.total_size
+                .fetch_add(remaining_count * info.size, Ordering::Relaxed);
+            crate::stats::add_thread_cache_bytes((remaining_count * info.size) as i64);
+        }
+
+        // Slow-start growth: this fetch happened because the list
+        // underflowed. Grow cautiously (by one) while still below
+        // batch_size -- the cautious phase -- then grow by a full batch
+        // once past it, capped at MAX_DYNAMIC_FREE_LIST_LENGTH.
+        let list = &mut self.lists[size_class];
+        list.recently_underflowed = true;
+        if list.max_length < batch_size {
+            list.max_length += 1;
+        } else {
+            list.max_length = (list.max_length + batch_size).min(MAX_DYNAMIC_FREE_LIST_LENGTH);
+        }
+
+        // Repeated underflows mean this thread's own cap is genuinely too
+        // small for its workload, not just a cold start -- try to grow it by
+        // stealing slack from whichever other registered thread is idlest.
+        self.underflow_count += 1;
+        if self.underflow_count >= GROWTH_CHECK_INTERVAL {
+            self.underflow_count = 0;
+            unsafe { manager::maybe_grow(self) };
         }
 
-        // Set max_length to accommodate the fetched batch so we don't
-        // immediately release everything back to central on the next dealloc.
+        (result as *mut u8, fresh)
+    }
+
+    /// Allocate up to `n` objects of `size_class` as a single linked list,
+    /// threaded through each object's intrusive `next` pointer exactly like
+    /// `FreeList` itself. Intended for a caller that already knows it wants
+    /// many objects of one class (a growing `Vec`/`HashMap`) and would
+    /// otherwise pay the free-list-head cost of `n` separate `allocate`
+    /// calls. Returns the actual count obtained, which is less than `n` only
+    /// if the central cache and page heap together ran short.
+    ///
+    /// Objects handed back here are leaving the thread cache for good (the
+    /// caller owns them now), so -- like the single-object `allocate` path --
+    /// no guard word needs writing under the `canary` feature.
+    #[inline]
+    pub unsafe fn allocate_batch(
+        &mut self,
+        size_class: usize,
+        n: u32,
+        central: &CentralCache,
+        page_heap: &SpinMutex<PageHeap>,
+        pagemap: &PageMap,
+    ) -> (u32, *mut FreeObject) {
+        let obj_size = size_class::class_to_size(size_class);
         let list = &mut self.lists[size_class];
-        if (list.max_length as usize) < count {
-            list.max_length = count as u32;
+        let (local_count, local_head) = list.pop_batch(n, obj_size);
+        if local_count > 0 {
+            self.total_size
+                .fetch_sub(local_count as usize * obj_size, Ordering::Relaxed);
+            crate::stats::add_thread_cache_bytes(-((local_count as usize * obj_size) as i64));
         }
+        if local_count >= n {
+            return (local_count, local_head);
+        }
+
+        // Local list came up short -- pull the rest straight from central
+        // in one `remove_range` call instead of refilling the thread cache
+        // first and immediately popping the refill back out.
+        let remaining = (n - local_count) as usize;
+        let (central_count, central_head, _fresh) = unsafe {
+            central
+                .get(size_class)
+                .lock()
+                .remove_range(remaining, page_heap, pagemap)
+        };
+        if central_count == 0 || central_head.is_null() {
+            return (local_count, local_head);
+        }
+
+        let total_count = local_count + central_count as u32;
+        if local_head.is_null() {
+            return (total_count, central_head);
+        }
+        let mut tail = central_head;
+        unsafe {
+            while !(*tail).next.is_null() {
+                tail = (*tail).next;
+            }
+            (*tail).next = local_head;
+        }
+        (total_count, central_head)
+    }
+
+    /// Deallocate a linked list of `count` objects of `size_class`, threaded
+    /// through `next` pointers exactly like `allocate_batch` returns them,
+    /// with one `total_size` adjustment instead of `count` separate ones.
+    /// The batch is pushed as non-fresh -- it came from general circulation,
+    /// not straight from a span never previously recycled.
+    #[inline]
+    pub unsafe fn deallocate_batch(
+        &mut self,
+        head: *mut FreeObject,
+        count: u32,
+        size_class: usize,
+        central: &CentralCache,
+        page_heap: &SpinMutex<PageHeap>,
+        pagemap: &PageMap,
+    ) {
+        if head.is_null() || count == 0 {
+            return;
+        }
+        let obj_size = size_class::class_to_size(size_class);
+        let list = &mut self.lists[size_class];
+        list.push_batch(head, count, false, obj_size);
 
-        result as *mut u8
+        self.total_size
+            .fetch_add(count as usize * obj_size, Ordering::Relaxed);
+        crate::stats::add_thread_cache_bytes((count as usize * obj_size) as i64);
+
+        if list.length > list.max_length {
+            unsafe { self.release_to_central(size_class, central, page_heap, pagemap) };
+        }
     }
 
-    /// Release excess objects from a size class back to central cache.
+    /// Release enough objects back to central cache to bring a size class
+    /// back under its `max_length`, called when a free just pushed it over.
+    ///
+    /// This is the *overflow* release, a different policy from `scavenge`'s
+    /// periodic low-water-mark GC: it releases a fixed amount -- whatever it
+    /// takes to get `length` back under `max_length`, or `batch_size`,
+    /// whichever is more -- and never touches `lowater`. `lowater`/the
+    /// observation window belong to `scavenge` alone; resetting them here
+    /// too would make that window reset on every overflow instead of every
+    /// `SCAVENGE_INTERVAL`, breaking the "coldest objects since the whole GC
+    /// interval" reasoning `scavenge` depends on.
+    ///
+    /// This is also the slow-start shrink point: if the list overflowed
+    /// without a fetch (no underflow) happening since we last checked, its
+    /// batch size is bigger than this class actually needs, so `max_length`
+    /// is eased back down toward `batch_size` instead of staying wherever
+    /// growth last left it.
     unsafe fn release_to_central(
         &mut self,
         size_class: usize,
@@ -210,16 +593,23 @@ impl ThreadCache {
         pagemap: &PageMap,
     ) {
         let info = size_class::class_info(size_class);
+        let batch_size = info.batch_size as u32;
+
         let list = &mut self.lists[size_class];
+        if !list.recently_underflowed && list.max_length > batch_size {
+            list.max_length = batch_size + (list.max_length - batch_size) / 2;
+        }
+        list.recently_underflowed = false;
 
-        // Release half of the objects
-        let to_release = list.length / 2;
+        let to_release = list.length.saturating_sub(list.max_length).max(batch_size);
         if to_release == 0 {
             return;
         }
 
-        let (count, head) = list.pop_batch(to_release);
-        self.total_size -= count as usize * info.size;
+        let (count, head) = list.pop_batch(to_release, info.size);
+        self.total_size
+            .fetch_sub(count as usize * info.size, Ordering::Relaxed);
+        crate::stats::add_thread_cache_bytes(-((count as usize * info.size) as i64));
 
         unsafe {
             central
@@ -227,39 +617,36 @@ impl ThreadCache {
                 .lock()
                 .insert_range(head, count as usize, page_heap, pagemap)
         };
-
-        // Shrink max_length if we keep overflowing
-        list.max_length = list.max_length.max(list.length);
     }
 
-    /// GC: release objects across all size classes to bring total_size under max_size.
+    /// Periodic low-water-mark GC: for every class, release exactly the
+    /// `lowater` objects that went untouched over the whole interval since
+    /// the last scavenge, then start a fresh observation window. Stops once
+    /// `total_size` reaches the `MIN_THREAD_CACHE_SIZE` floor so a thread
+    /// that's gone idle still keeps a minimum working set resident.
     unsafe fn scavenge(
         &mut self,
         central: &CentralCache,
         page_heap: &SpinMutex<PageHeap>,
         pagemap: &PageMap,
     ) {
-        // Target: bring total_size down to max_size / 2
-        let target = self.max_size / 2;
-
         for cls in 1..NUM_SIZE_CLASSES {
-            if self.total_size <= target {
+            if self.total_size.load(Ordering::Relaxed) <= MIN_THREAD_CACHE_SIZE {
                 break;
             }
 
             let list = &mut self.lists[cls];
-            if list.length == 0 {
-                continue;
-            }
-
-            let info = size_class::class_info(cls);
-            let to_release = list.length / 2;
+            let to_release = list.lowater;
+            list.lowater = list.length;
             if to_release == 0 {
                 continue;
             }
 
-            let (count, head) = list.pop_batch(to_release);
-            self.total_size -= count as usize * info.size;
+            let info = size_class::class_info(cls);
+            let (count, head) = list.pop_batch(to_release, info.size);
+            self.total_size
+                .fetch_sub(count as usize * info.size, Ordering::Relaxed);
+            crate::stats::add_thread_cache_bytes(-((count as usize * info.size) as i64));
 
             unsafe {
                 central
@@ -271,6 +658,345 @@ impl ThreadCache {
     }
 }
 
+/// Global registry and shared-budget enforcement across every `ThreadCache`.
+///
+/// `MAX_THREAD_CACHE_SIZE` alone gives every thread the same fixed cap
+/// regardless of how active it is, so a process with many mostly-idle
+/// threads can end up caching far more memory than it's actually using.
+/// `ThreadCacheManager` turns that fixed cap into a shared, work-conserving
+/// budget: every `ThreadCache` registers itself here when it's first
+/// initialized and deregisters when its owning thread exits, and the manager
+/// nudges individual `max_size`s up or down so the combined total tracks
+/// [`set_overall_cache_size`]'s budget instead of `thread_count *
+/// MAX_THREAD_CACHE_SIZE`.
+///
+/// Rebalancing never runs code on another thread. It only ever mutates the
+/// victim/donor's `max_size` (an `AtomicUsize` for exactly this reason) --
+/// the existing `total_size > max_size` check in [`ThreadCache::deallocate`]
+/// means a thread whose cap just got cut notices and scavenges itself down
+/// to the new limit the next time it frees anything.
+mod manager {
+    use super::{
+        ThreadCache, DEFAULT_OVERALL_CACHE_SIZE, MIN_THREAD_CACHE_SIZE, STEAL_CHUNK,
+    };
+    use crate::sync::SpinMutex;
+    use core::cell::Cell;
+    use core::ptr;
+    use core::sync::atomic::Ordering;
+    use std::thread_local;
+
+    /// Global list of every live `ThreadCache`, linked intrusively through
+    /// `ThreadCache::manager_next`/`manager_prev` so the manager itself never
+    /// needs to allocate to track its own bookkeeping.
+    struct ManagerState {
+        head: *mut ThreadCache,
+        overall_size: usize,
+    }
+
+    // Safety: every pointer in `head`'s list stays valid for as long as its
+    // owning thread is alive (see `register_current_thread`), and the list
+    // itself is only ever walked or mutated while holding `MANAGER`'s lock.
+    unsafe impl Send for ManagerState {}
+
+    static MANAGER: SpinMutex<ManagerState> = SpinMutex::new(ManagerState {
+        head: ptr::null_mut(),
+        overall_size: DEFAULT_OVERALL_CACHE_SIZE,
+    });
+
+    /// Set the combined byte budget shared by every thread's cache. Existing
+    /// thread caches adopt the new limit the next time they overflow,
+    /// underflow, or get rebalanced against another thread -- nothing is
+    /// evicted eagerly by this call itself.
+    pub fn set_overall_cache_size(bytes: usize) {
+        MANAGER.lock().overall_size = bytes;
+    }
+
+    /// The currently configured overall budget.
+    pub fn overall_cache_size() -> usize {
+        MANAGER.lock().overall_size
+    }
+
+    /// Best-effort total of bytes cached across every thread, used to decide
+    /// whether the shared budget has been reached. Backed by the same
+    /// running counter `stats::thread_cache_bytes` exposes.
+    pub fn total_cached_bytes() -> usize {
+        crate::stats::thread_cache_bytes() as usize
+    }
+
+    /// Fair per-thread share of the overall budget: the budget divided evenly
+    /// across every currently registered thread. `maybe_steal`/`maybe_grow`
+    /// nudge individual thread caches toward this line rather than enforcing
+    /// it exactly, since forcing it exactly would require synchronously
+    /// interrupting another thread.
+    fn fair_share(state: &ManagerState) -> usize {
+        let mut count = 0usize;
+        let mut node = state.head;
+        while !node.is_null() {
+            count += 1;
+            node = unsafe { (*node).manager_next };
+        }
+        if count == 0 {
+            state.overall_size
+        } else {
+            (state.overall_size / count).max(MIN_THREAD_CACHE_SIZE)
+        }
+    }
+
+    /// Link `tc` into the global registry.
+    ///
+    /// # Safety
+    /// `tc` must stay valid until a matching `deregister` call.
+    unsafe fn register(tc: *mut ThreadCache) {
+        let mut state = MANAGER.lock();
+        unsafe {
+            (*tc).manager_next = state.head;
+            (*tc).manager_prev = ptr::null_mut();
+            if let Some(old_head) = state.head.as_mut() {
+                old_head.manager_prev = tc;
+            }
+        }
+        state.head = tc;
+    }
+
+    /// Unlink `tc` from the global registry.
+    ///
+    /// # Safety
+    /// `tc` must currently be registered.
+    unsafe fn deregister(tc: *mut ThreadCache) {
+        let mut state = MANAGER.lock();
+        unsafe {
+            let next = (*tc).manager_next;
+            let prev = (*tc).manager_prev;
+            if let Some(prev) = prev.as_mut() {
+                prev.manager_next = next;
+            } else {
+                state.head = next;
+            }
+            if let Some(next) = next.as_mut() {
+                next.manager_prev = prev;
+            }
+        }
+    }
+
+    /// Shrink whichever registered thread cache has drifted furthest above
+    /// its fair share of the overall budget. Called from `deallocate` once
+    /// the combined total has reached the cap.
+    ///
+    /// # Safety
+    /// Must only be called with every currently registered `ThreadCache`
+    /// pointer valid, which holds as long as callers only reach this through
+    /// `ThreadCache::deallocate` on a live, registered cache.
+    pub fn maybe_steal() {
+        let state = MANAGER.lock();
+        let share = fair_share(&state);
+        let mut victim: *mut ThreadCache = ptr::null_mut();
+        let mut worst_excess = 0usize;
+        let mut node = state.head;
+        while !node.is_null() {
+            unsafe {
+                let current = (*node).max_size.load(Ordering::Relaxed);
+                let excess = current.saturating_sub(share);
+                if excess > worst_excess {
+                    worst_excess = excess;
+                    victim = node;
+                }
+                node = (*node).manager_next;
+            }
+        }
+        drop(state);
+
+        if victim.is_null() || worst_excess == 0 {
+            return;
+        }
+        unsafe {
+            let current = (*victim).max_size.load(Ordering::Relaxed);
+            let new_limit = current.saturating_sub(STEAL_CHUNK).max(MIN_THREAD_CACHE_SIZE);
+            (*victim).max_size.store(new_limit, Ordering::Relaxed);
+        }
+    }
+
+    /// Grow `tc`'s own budget by stealing idle capacity from whichever other
+    /// registered thread has the most slack between what it's allowed
+    /// (`max_size`) and what it's actually holding (`total_size`) -- i.e. the
+    /// most idle thread. Called from `fetch_from_central` once a thread has
+    /// underflowed repeatedly, which suggests its cap is genuinely too small
+    /// rather than just a cold start.
+    ///
+    /// # Safety
+    /// `tc` must be the calling thread's own, currently registered,
+    /// `ThreadCache`.
+    pub unsafe fn maybe_grow(tc: *mut ThreadCache) {
+        let state = MANAGER.lock();
+        let mut donor: *mut ThreadCache = ptr::null_mut();
+        let mut best_slack = 0usize;
+        let mut node = state.head;
+        while !node.is_null() {
+            unsafe {
+                if node != tc {
+                    let donor_max = (*node).max_size.load(Ordering::Relaxed);
+                    let donor_total = (*node).total_size.load(Ordering::Relaxed);
+                    let slack = donor_max.saturating_sub(donor_total);
+                    if slack > best_slack {
+                        best_slack = slack;
+                        donor = node;
+                    }
+                }
+                node = (*node).manager_next;
+            }
+        }
+        drop(state);
+
+        if donor.is_null() || best_slack < STEAL_CHUNK {
+            return;
+        }
+
+        unsafe {
+            let donor_max = (*donor).max_size.load(Ordering::Relaxed);
+            (*donor)
+                .max_size
+                .store(donor_max - STEAL_CHUNK, Ordering::Relaxed);
+            (*tc).max_size.fetch_add(STEAL_CHUNK, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops alongside the owning thread's std TLS teardown, which is what
+    /// actually runs `deregister` -- the raw `#[thread_local]` statics this
+    /// crate otherwise uses for `ThreadCache` itself have no destructor of
+    /// their own, so this guard is kept in an ordinary `std::thread_local!`
+    /// purely to get a `Drop` call at thread exit.
+    struct RegistrationGuard {
+        cache: Cell<*mut ThreadCache>,
+    }
+
+    impl Drop for RegistrationGuard {
+        fn drop(&mut self) {
+            let tc = self.cache.get();
+            if !tc.is_null() {
+                unsafe { deregister(tc) };
+            }
+        }
+    }
+
+    thread_local! {
+        static REGISTRATION: RegistrationGuard = RegistrationGuard { cache: Cell::new(ptr::null_mut()) };
+    }
+
+    /// Register `tc` -- the calling thread's own `ThreadCache` -- with the
+    /// global manager, and arrange for it to be automatically deregistered
+    /// when the thread exits.
+    ///
+    /// # Safety
+    /// `tc` must remain valid for as long as the calling thread is alive,
+    /// which holds for the `#[thread_local]` statics this is called from.
+    pub unsafe fn register_current_thread(tc: *mut ThreadCache) {
+        REGISTRATION.with(|guard| guard.cache.set(tc));
+        unsafe { register(tc) };
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::central_free_list::CentralCache;
+        use crate::page_heap::PageHeap;
+        use crate::pagemap::PageMap;
+
+        /// A single test function rather than several, since every test
+        /// would otherwise share the one process-wide `MANAGER` static and
+        /// cargo's default parallel test execution would let them stomp on
+        /// each other's registrations.
+        #[test]
+        fn test_manager_register_deregister_and_concurrent_rebalance() {
+            // Single-threaded sanity check of register/deregister/fair_share
+            // and registry-list integrity, using manually-managed
+            // `ThreadCache`s (registered/deregistered within this same stack
+            // frame, unlike `register_current_thread`, which ties
+            // deregistration to a thread-local destructor and so requires
+            // `tc` to live in real TLS, not on the stack).
+            let mut a = ThreadCache::new();
+            let mut b = ThreadCache::new();
+            let a_ptr = &mut a as *mut ThreadCache;
+            let b_ptr = &mut b as *mut ThreadCache;
+            unsafe {
+                register(a_ptr);
+                register(b_ptr);
+            }
+            {
+                let state = MANAGER.lock();
+                assert_eq!(fair_share(&state), state.overall_size / 2);
+                // Both links should be walkable and distinct.
+                let mut seen = 0;
+                let mut node = state.head;
+                while !node.is_null() {
+                    seen += 1;
+                    node = unsafe { (*node).manager_next };
+                }
+                assert_eq!(seen, 2);
+            }
+            unsafe {
+                deregister(a_ptr);
+                deregister(b_ptr);
+            }
+            {
+                let state = MANAGER.lock();
+                assert!(state.head.is_null());
+                assert_eq!(fair_share(&state), state.overall_size);
+            }
+
+            // Multi-threaded: register several real thread caches, drive
+            // allocation pressure through them concurrently (enough to push
+            // past GROWTH_CHECK_INTERVAL/SCAVENGE_INTERVAL so both
+            // maybe_steal and maybe_grow actually run), then confirm the
+            // registry is left in a consistent state with no leaked entries
+            // and every cache's own free list still usable afterward.
+            let pm: &'static PageMap = Box::leak(Box::new(PageMap::new()));
+            let heap: &'static SpinMutex<PageHeap> =
+                Box::leak(Box::new(SpinMutex::new(PageHeap::new(pm))));
+            let central: &'static CentralCache = Box::leak(Box::new(CentralCache::new()));
+
+            set_overall_cache_size(2 * MIN_THREAD_CACHE_SIZE * 4);
+
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    std::thread::spawn(move || {
+                        let mut tc = ThreadCache::new();
+                        let tc_ptr = &mut tc as *mut ThreadCache;
+                        unsafe { register(tc_ptr) };
+
+                        unsafe {
+                            let mut ptrs = Vec::new();
+                            for _ in 0..4000 {
+                                let ptr = tc.allocate(4, central, heap, pm);
+                                assert!(!ptr.is_null());
+                                ptrs.push(ptr);
+                                if ptrs.len() > 32 {
+                                    let p = ptrs.remove(0);
+                                    tc.deallocate(p, 4, central, heap, pm);
+                                }
+                            }
+                            for p in ptrs {
+                                tc.deallocate(p, 4, central, heap, pm);
+                            }
+                        }
+
+                        unsafe { deregister(tc_ptr) };
+                    })
+                })
+                .collect();
+            for h in handles {
+                h.join().unwrap();
+            }
+
+            let state = MANAGER.lock();
+            assert!(
+                state.head.is_null(),
+                "manager registry leaked an entry after every thread deregistered"
+            );
+        }
+    }
+}
+
+pub use manager::{register_current_thread, set_overall_cache_size};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,6 +1069,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_allocate_size() {
+        let (pm, heap, central) = make_test_env();
+        let mut tc = ThreadCache::new();
+
+        unsafe {
+            // 20 bytes rounds up to the 24-byte class.
+            let (ptr, class) = tc.allocate_size(20, &central, &heap, pm);
+            assert!(!ptr.is_null());
+            assert_eq!(size_class::class_to_size(class), 24);
+            tc.deallocate(ptr, class, &central, &heap, pm);
+
+            // Larger than the biggest small class: falls through to null/0
+            // instead of the caller having to recognize a `0` sentinel class.
+            let (ptr, class) = tc.allocate_size(size_class::MAX_SMALL_SIZE + 1, &central, &heap, pm);
+            assert!(ptr.is_null());
+            assert_eq!(class, 0);
+        }
+    }
+
     #[test]
     fn test_reuse_from_cache() {
         let (pm, heap, central) = make_test_env();
@@ -362,4 +1108,141 @@ mod tests {
             tc.deallocate(ptr2, 2, &central, &heap, pm);
         }
     }
+
+    #[test]
+    fn test_release_to_central_overflows_with_zero_lowater() {
+        // `lowater` only ever updates on pop, and this test never pops from
+        // the list after it's first populated -- lowater stays stuck at 0
+        // for the whole run, exactly the case that used to leave a list
+        // stuck over max_length forever, since overflow release used to mean
+        // "release `lowater` objects".
+        let (pm, heap, central) = make_test_env();
+        let mut tc = ThreadCache::new();
+        let cls = 4;
+
+        unsafe {
+            let mut ptrs = Vec::new();
+            for _ in 0..2000 {
+                let ptr = tc.allocate(cls, &central, &heap, pm);
+                assert!(!ptr.is_null());
+                ptrs.push(ptr);
+            }
+            for ptr in ptrs {
+                tc.deallocate(ptr, cls, &central, &heap, pm);
+            }
+
+            let list = &tc.lists[cls];
+            assert!(
+                list.length <= list.max_length,
+                "overflow release should bring length back under max_length even \
+                 with lowater == 0, got length={} max_length={}",
+                list.length,
+                list.max_length
+            );
+        }
+    }
+
+    #[cfg(feature = "canary")]
+    #[test]
+    fn test_canary_round_trip() {
+        // Guard words written on push must survive an ordinary push/pop with
+        // no tampering in between -- i.e. `canary` alone doesn't false-positive.
+        let (pm, heap, central) = make_test_env();
+        let mut tc = ThreadCache::new();
+        unsafe {
+            let mut ptrs = Vec::new();
+            for _ in 0..64 {
+                let ptr = tc.allocate(4, &central, &heap, pm); // class 4 = 32 bytes
+                assert!(!ptr.is_null());
+                ptrs.push(ptr);
+            }
+            for ptr in &ptrs {
+                tc.deallocate(*ptr, 4, &central, &heap, pm);
+            }
+            for _ in 0..64 {
+                let ptr = tc.allocate(4, &central, &heap, pm);
+                assert!(!ptr.is_null());
+            }
+        }
+    }
+
+    #[cfg(all(feature = "canary", feature = "hardened"))]
+    #[test]
+    fn test_canary_and_hardened_compatible() {
+        // Regression test: `hardened` used to scrub/verify the same word
+        // `canary` writes its guard into, which made every alloc/free/alloc
+        // cycle on any class > 8 bytes abort. Exercise both in the exact
+        // order `TcMalloc::dealloc_small`/`alloc_small` call them in.
+        let (pm, heap, central) = make_test_env();
+        let mut tc = ThreadCache::new();
+        let class_size = size_class::class_to_size(4); // 32 bytes
+        unsafe {
+            let ptr = tc.allocate(4, &central, &heap, pm);
+            assert!(!ptr.is_null());
+
+            crate::hardened::scrub_on_free(ptr, class_size);
+            tc.deallocate(ptr, 4, &central, &heap, pm);
+
+            let ptr2 = tc.allocate(4, &central, &heap, pm);
+            assert!(!ptr2.is_null());
+            crate::hardened::verify_on_alloc(ptr2, class_size);
+        }
+    }
+
+    /// Re-exec the current test binary filtered to just `test_name`, with
+    /// `RSTCMALLOC_CANARY_ABORT_CHILD` set so the filtered test runs its
+    /// abort-triggering body for real in a fresh process, instead of just
+    /// asserting a real abort inline (which would kill this test binary too).
+    #[cfg(feature = "canary")]
+    fn assert_aborts(test_name: &str) {
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--exact")
+            .arg(test_name)
+            .arg("--nocapture")
+            .env("RSTCMALLOC_CANARY_ABORT_CHILD", "1")
+            .status()
+            .expect("failed to re-exec test binary");
+        assert!(
+            !status.success(),
+            "expected {test_name} to abort the process, it exited successfully"
+        );
+    }
+
+    #[cfg(feature = "canary")]
+    #[test]
+    fn test_canary_detects_write_after_free() {
+        if std::env::var_os("RSTCMALLOC_CANARY_ABORT_CHILD").is_some() {
+            let (pm, heap, central) = make_test_env();
+            let mut tc = ThreadCache::new();
+            unsafe {
+                let ptr = tc.allocate(4, &central, &heap, pm);
+                tc.deallocate(ptr, 4, &central, &heap, pm);
+                // Corrupt the guard word itself -- a write into memory that's
+                // supposed to be free -- then pop should abort.
+                let guard_slot = ptr.add(size_of::<*mut FreeObject>());
+                guard_slot.cast::<usize>().write(0xDEAD_BEEF);
+                let _ = tc.allocate(4, &central, &heap, pm);
+            }
+            return;
+        }
+        assert_aborts("thread_cache::tests::test_canary_detects_write_after_free");
+    }
+
+    #[cfg(feature = "canary")]
+    #[test]
+    fn test_canary_detects_double_free_at_head() {
+        if std::env::var_os("RSTCMALLOC_CANARY_ABORT_CHILD").is_some() {
+            let (pm, heap, central) = make_test_env();
+            let mut tc = ThreadCache::new();
+            unsafe {
+                let ptr = tc.allocate(4, &central, &heap, pm);
+                tc.deallocate(ptr, 4, &central, &heap, pm);
+                // Same pointer, still at the list head -- the cheap
+                // single-step double-free check should abort.
+                tc.deallocate(ptr, 4, &central, &heap, pm);
+            }
+            return;
+        }
+        assert_aborts("thread_cache::tests::test_canary_detects_double_free_at_head");
+    }
 }